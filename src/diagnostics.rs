@@ -0,0 +1,116 @@
+use crate::error::BunkuError;
+use crate::layers::SourceFile;
+use crate::schema;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+
+/// Where a value is written, 1-based like editors and CI annotations expect.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// Locate a byte offset into `content`.
+    pub fn at(file: &str, content: &str, offset: usize) -> Self {
+        let before = &content[..offset.min(content.len())];
+        Location {
+            file: file.to_string(),
+            line: before.matches('\n').count() + 1,
+            column: before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// One problem found by `validate`, with the TOML path and location of the
+/// value when they are known.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    #[serde(flatten)]
+    pub location: Option<Location>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(location) = &self.location {
+            write!(f, "{}: ", location)?;
+        }
+        write!(f, "{}: ", self.severity)?;
+        if let Some(path) = &self.path {
+            write!(f, "{}: ", path)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Split an error into one diagnostic per problem, locating each value in the
+/// files it was read from.
+pub fn diagnose(error: BunkuError, severity: Severity, sources: &[SourceFile]) -> Vec<Diagnostic> {
+    let diagnostic = |location, path, message| Diagnostic {
+        severity,
+        location,
+        path,
+        message,
+    };
+
+    match error {
+        BunkuError::Multiple(errors) => errors
+            .into_iter()
+            .flat_map(|error| diagnose(error, severity, sources))
+            .collect(),
+        BunkuError::UnknownKeys(keys) => keys
+            .into_iter()
+            .map(|key| {
+                let message = key.message();
+                diagnostic(key.location, Some(key.path), message)
+            })
+            .collect(),
+        BunkuError::Invalid { path, message } => {
+            let location = schema::locate_path(&path, sources);
+            vec![diagnostic(location, Some(path), message)]
+        }
+        // Syntax errors come before any source is kept, so read the file again
+        BunkuError::File { path, source } => {
+            if let BunkuError::TomlParsing(error) = source.as_ref()
+                && let Some(span) = error.span()
+                && let Ok(content) = fs::read_to_string(&path)
+            {
+                let location = Location::at(&path, &content, span.start);
+                let message = error.message().lines().collect::<Vec<_>>().join(": ");
+                return vec![diagnostic(Some(location), None, message)];
+            }
+            let message = BunkuError::File { path, source }.to_string();
+            vec![diagnostic(None, None, message)]
+        }
+        error => vec![diagnostic(None, None, error.to_string())],
+    }
+}
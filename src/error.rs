@@ -1,3 +1,4 @@
+use crate::schema::UnknownKey;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -11,6 +12,84 @@ pub enum BunkuError {
     #[error("JSON serialization error: {0}")]
     JsonSerialization(#[from] serde_json::Error),
 
+    #[error("YAML serialization error: {0}")]
+    YamlSerialization(#[from] serde_yaml_ng::Error),
+
     #[error("Validation error: {0}")]
     Validation(String),
+
+    /// A problem with one value, named by its TOML path.
+    #[error("{path}: {message}")]
+    Invalid { path: String, message: String },
+
+    #[error("unknown keys in values:\n{}", list(.0))]
+    UnknownKeys(Vec<UnknownKey>),
+
+    #[error("{} problems found:\n{}", .0.len(), list(.0))]
+    Multiple(Vec<BunkuError>),
+
+    #[error("{path}: {source}")]
+    File {
+        path: String,
+        #[source]
+        source: Box<BunkuError>,
+    },
+}
+
+impl BunkuError {
+    pub fn invalid(path: impl Into<String>, message: impl ToString) -> Self {
+        BunkuError::Invalid {
+            path: path.into(),
+            message: message.to_string(),
+        }
+    }
+}
+
+fn list<T: ToString>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Collects errors so one run reports every problem instead of the first.
+/// A problem found by more than one check is kept once.
+#[derive(Debug, Default)]
+pub struct Errors(Vec<BunkuError>);
+
+impl Errors {
+    pub fn push(&mut self, error: BunkuError) {
+        match error {
+            BunkuError::Multiple(errors) => errors.into_iter().for_each(|e| self.push(e)),
+            error => {
+                if !self.contains(&error) {
+                    self.0.push(error);
+                }
+            }
+        }
+    }
+
+    /// Record the error of a failed result, returning the value otherwise.
+    pub fn capture<T>(&mut self, result: Result<T, BunkuError>) -> Option<T> {
+        result.map_err(|error| self.push(error)).ok()
+    }
+
+    /// Whether a problem with the same message was already recorded.
+    pub fn contains(&self, error: &BunkuError) -> bool {
+        let message = error.to_string();
+        self.0.iter().any(|known| known.to_string() == message)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn finish(mut self) -> Result<(), BunkuError> {
+        match self.0.len() {
+            0 => Ok(()),
+            1 => Err(self.0.remove(0)),
+            _ => Err(BunkuError::Multiple(self.0)),
+        }
+    }
 }
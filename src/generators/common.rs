@@ -1,9 +1,27 @@
-use crate::values::{PodOptions, Values};
+use crate::error::{BunkuError, Errors};
+use crate::values::{Controller, PodOptions, Values};
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 use std::collections::BTreeMap;
 
-pub fn generate_name(_values: &Values, resource_name: &str) -> String {
-    resource_name.to_string()
+/// Name of a generated object, prefixed with the release name when one is set
+/// so every reference to it agrees.
+pub fn generate_name(values: &Values, resource_name: &str) -> String {
+    match &values.global.release_name {
+        Some(release_name) => format!("{}-{}", release_name, resource_name),
+        None => resource_name.to_string(),
+    }
+}
+
+/// Value of the `app.kubernetes.io/instance` label.
+pub fn instance_name(values: &Values) -> String {
+    values
+        .global
+        .release_name
+        .clone()
+        .unwrap_or_else(|| "release".to_string())
 }
 
 pub fn generate_labels(
@@ -20,7 +38,7 @@ pub fn generate_labels(
     );
     labels.insert(
         "app.kubernetes.io/instance".to_string(),
-        "release".to_string(),
+        instance_name(values),
     );
 
     // Add resource-specific labels
@@ -70,6 +88,41 @@ pub fn generate_metadata(values: &Values, resource_name: &str, resource_type: &s
     }
 }
 
+/// Metadata for a workload object, including the controller's own labels and
+/// annotations (e.g. Helm or Argo CD hook annotations).
+pub fn generate_controller_metadata(
+    values: &Values,
+    resource_name: &str,
+    controller: &Controller,
+) -> ObjectMeta {
+    let mut labels = generate_labels(values, resource_name, "controller");
+    labels.extend(
+        controller
+            .labels
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone())),
+    );
+
+    let mut annotations = generate_annotations(values);
+    annotations.extend(
+        controller
+            .annotations
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone())),
+    );
+
+    ObjectMeta {
+        name: Some(generate_name(values, resource_name)),
+        labels: Some(labels),
+        annotations: if annotations.is_empty() {
+            None
+        } else {
+            Some(annotations)
+        },
+        ..Default::default()
+    }
+}
+
 pub fn generate_selector_labels(values: &Values, resource_name: &str) -> BTreeMap<String, String> {
     let mut labels = BTreeMap::new();
     labels.insert(
@@ -78,7 +131,7 @@ pub fn generate_selector_labels(values: &Values, resource_name: &str) -> BTreeMa
     );
     labels.insert(
         "app.kubernetes.io/instance".to_string(),
-        "release".to_string(),
+        instance_name(values),
     );
     labels.insert(
         "app.kubernetes.io/controller".to_string(),
@@ -87,96 +140,201 @@ pub fn generate_selector_labels(values: &Values, resource_name: &str) -> BTreeMa
     labels
 }
 
+/// Parse rolling update values such as `"1"` or `"25%"` into an IntOrString,
+/// keeping plain numbers as integers since the API rejects non-percent strings.
+pub fn int_or_string(value: &str) -> IntOrString {
+    match value.parse::<i32>() {
+        Ok(number) => IntOrString::Int(number),
+        Err(_) => IntOrString::String(value.to_string()),
+    }
+}
+
+/// Deserialise an untyped value into its Kubernetes type. Errors name the
+/// full path to the offending value, e.g. `...livenessProbe.httpGet.port`.
+pub fn convert_value<T: DeserializeOwned>(
+    path: &str,
+    field: &str,
+    value: &Value,
+) -> Result<T, BunkuError> {
+    serde_path_to_error::deserialize(value.clone()).map_err(|e| {
+        let inner = e.path().to_string();
+        let path = match inner.as_str() {
+            "." => format!("{}.{}", path, field),
+            _ if inner.starts_with('[') => format!("{}.{}{}", path, field, inner),
+            _ => format!("{}.{}.{}", path, field, inner),
+        };
+        BunkuError::invalid(path, e.into_inner())
+    })
+}
+
+pub fn convert_optional<T: DeserializeOwned>(
+    path: &str,
+    field: &str,
+    value: &Option<Value>,
+) -> Result<Option<T>, BunkuError> {
+    value
+        .as_ref()
+        .map(|value| convert_value(path, field, value))
+        .transpose()
+}
+
+/// Convert every entry of a list, reporting each one that fails. Empty lists
+/// become `None`.
+pub fn convert_list<T: DeserializeOwned>(
+    path: &str,
+    field: &str,
+    items: Option<&[Value]>,
+) -> Result<Option<Vec<T>>, BunkuError> {
+    let Some(items) = items.filter(|items| !items.is_empty()) else {
+        return Ok(None);
+    };
+
+    let mut errors = Errors::default();
+    let converted: Vec<T> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            errors.capture(convert_value(path, &format!("{}[{}]", field, index), item))
+        })
+        .collect();
+    errors.finish()?;
+    Ok(Some(converted))
+}
+
+/// Combine the default pod options with a controller's own.
+///
+/// With `overwrite`, every field the controller sets replaces the default.
+/// With `merge`, maps merge by key, lists are appended or replaced according to
+/// `list_strategy`, structured values are merged recursively and scalars set by
+/// the controller win. Fields the controller leaves unset keep the default.
 pub fn merge_pod_options(
     default_options: &PodOptions,
     controller_options: &PodOptions,
     strategy: &str,
-) -> PodOptions {
-    match strategy {
-        "merge" => {
-            let mut merged = default_options.clone();
-
-            // Merge annotations
-            merged
-                .annotations
-                .extend(controller_options.annotations.clone());
-
-            // Merge labels
-            merged.labels.extend(controller_options.labels.clone());
-
-            // Override other fields if they are set in controller options
-            if controller_options.automount_service_account_token {
-                merged.automount_service_account_token =
-                    controller_options.automount_service_account_token;
-            }
-            if controller_options.dns_policy.is_some() {
-                merged.dns_policy = controller_options.dns_policy.clone();
-            }
-            if !controller_options.enable_service_links {
-                merged.enable_service_links = controller_options.enable_service_links;
-            }
-            if controller_options.hostname.is_some() {
-                merged.hostname = controller_options.hostname.clone();
-            }
-            if controller_options.host_ipc {
-                merged.host_ipc = controller_options.host_ipc;
-            }
-            if controller_options.host_network {
-                merged.host_network = controller_options.host_network;
-            }
-            if controller_options.host_pid {
-                merged.host_pid = controller_options.host_pid;
-            }
-
-            merged
+    list_strategy: &str,
+) -> Result<PodOptions, BunkuError> {
+    let deep = match strategy {
+        "overwrite" => false,
+        "merge" => true,
+        _ => {
+            return Err(BunkuError::invalid(
+                "defaultPodOptionsStrategy",
+                format!(
+                    "unknown strategy {:?}, expected \"overwrite\" or \"merge\"",
+                    strategy
+                ),
+            ));
+        }
+    };
+    let append = match list_strategy {
+        "append" => true,
+        "replace" => false,
+        _ => {
+            return Err(BunkuError::invalid(
+                "defaultPodOptionsListStrategy",
+                format!(
+                    "unknown strategy {:?}, expected \"append\" or \"replace\"",
+                    list_strategy
+                ),
+            ));
         }
-        "overwrite" => {
-            // Use controller options if they have non-default values, otherwise use defaults
-            let mut result = controller_options.clone();
+    };
 
-            // Only use defaults if controller options are empty/default
-            if result.annotations.is_empty() {
-                result.annotations = default_options.annotations.clone();
-            }
-            if result.labels.is_empty() {
-                result.labels = default_options.labels.clone();
-            }
-            if result.node_selector.is_empty() {
-                result.node_selector = default_options.node_selector.clone();
-            }
-            if result.tolerations.is_empty() {
-                result.tolerations = default_options.tolerations.clone();
-            }
-            if result.topology_spread_constraints.is_empty() {
-                result.topology_spread_constraints =
-                    default_options.topology_spread_constraints.clone();
-            }
+    fn scalar<T: Clone>(default: &Option<T>, controller: &Option<T>) -> Option<T> {
+        controller.clone().or_else(|| default.clone())
+    }
 
-            result
+    let map = |default: &Option<BTreeMap<String, String>>,
+               controller: &Option<BTreeMap<String, String>>| {
+        match (default, controller) {
+            (Some(default), Some(controller)) if deep => {
+                let mut merged = default.clone();
+                merged.extend(controller.clone());
+                Some(merged)
+            }
+            _ => scalar(default, controller),
         }
-        _ => {
-            // Default case - same as overwrite for any unknown strategy
-            let mut result = controller_options.clone();
+    };
 
-            // Only use defaults if controller options are empty/default
-            if result.annotations.is_empty() {
-                result.annotations = default_options.annotations.clone();
-            }
-            if result.labels.is_empty() {
-                result.labels = default_options.labels.clone();
-            }
-            if result.node_selector.is_empty() {
-                result.node_selector = default_options.node_selector.clone();
-            }
-            if result.tolerations.is_empty() {
-                result.tolerations = default_options.tolerations.clone();
+    let list =
+        |default: &Option<Vec<Value>>, controller: &Option<Vec<Value>>| match (default, controller)
+        {
+            (Some(default), Some(controller)) if deep && append => {
+                Some(default.iter().chain(controller).cloned().collect())
             }
-            if result.topology_spread_constraints.is_empty() {
-                result.topology_spread_constraints =
-                    default_options.topology_spread_constraints.clone();
+            _ => scalar(default, controller),
+        };
+
+    let object = |default: &Option<Value>, controller: &Option<Value>| match (default, controller) {
+        (Some(default), Some(controller)) if deep => Some(merge_json(default, controller, append)),
+        _ => scalar(default, controller),
+    };
+
+    let d = default_options;
+    let c = controller_options;
+
+    Ok(PodOptions {
+        affinity: object(&d.affinity, &c.affinity),
+        annotations: map(&d.annotations, &c.annotations),
+        automount_service_account_token: scalar(
+            &d.automount_service_account_token,
+            &c.automount_service_account_token,
+        ),
+        dns_config: object(&d.dns_config, &c.dns_config),
+        dns_policy: scalar(&d.dns_policy, &c.dns_policy),
+        enable_service_links: scalar(&d.enable_service_links, &c.enable_service_links),
+        hostname: scalar(&d.hostname, &c.hostname),
+        host_aliases: list(&d.host_aliases, &c.host_aliases),
+        host_ipc: scalar(&d.host_ipc, &c.host_ipc),
+        host_network: scalar(&d.host_network, &c.host_network),
+        host_pid: scalar(&d.host_pid, &c.host_pid),
+        host_users: scalar(&d.host_users, &c.host_users),
+        image_pull_secrets: match (&d.image_pull_secrets, &c.image_pull_secrets) {
+            (Some(default), Some(controller)) if deep && append => {
+                let mut merged = default.clone();
+                merged.extend(controller.iter().filter(|s| !default.contains(s)).cloned());
+                Some(merged)
             }
+            (default, controller) => scalar(default, controller),
+        },
+        labels: map(&d.labels, &c.labels),
+        node_selector: map(&d.node_selector, &c.node_selector),
+        priority_class_name: scalar(&d.priority_class_name, &c.priority_class_name),
+        restart_policy: scalar(&d.restart_policy, &c.restart_policy),
+        runtime_class_name: scalar(&d.runtime_class_name, &c.runtime_class_name),
+        scheduler_name: scalar(&d.scheduler_name, &c.scheduler_name),
+        security_context: object(&d.security_context, &c.security_context),
+        share_process_namespace: scalar(&d.share_process_namespace, &c.share_process_namespace),
+        termination_grace_period_seconds: scalar(
+            &d.termination_grace_period_seconds,
+            &c.termination_grace_period_seconds,
+        ),
+        tolerations: list(&d.tolerations, &c.tolerations),
+        topology_spread_constraints: list(
+            &d.topology_spread_constraints,
+            &c.topology_spread_constraints,
+        ),
+    })
+}
 
-            result
+/// Recursively merge `overlay` into `base`: objects merge by key, arrays are
+/// appended or replaced, and anything else in the overlay wins.
+fn merge_json(base: &Value, overlay: &Value, append: bool) -> Value {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            let mut merged = base.clone();
+            for (key, value) in overlay {
+                let value = match merged.get(key) {
+                    Some(existing) => merge_json(existing, value, append),
+                    None => value.clone(),
+                };
+                merged.insert(key.clone(), value);
+            }
+            Value::Object(merged)
+        }
+        (Value::Array(base), Value::Array(overlay)) if append => {
+            Value::Array(base.iter().chain(overlay).cloned().collect())
         }
+        _ => overlay.clone(),
     }
 }
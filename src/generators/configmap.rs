@@ -1,4 +1,5 @@
 use super::{ResourceGenerator, common};
+use crate::error::BunkuError;
 use crate::values::Values;
 use k8s_openapi::api::core::v1::ConfigMap;
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
@@ -9,11 +10,13 @@ pub struct ConfigMapGenerator;
 impl ResourceGenerator for ConfigMapGenerator {
     type Output = ConfigMap;
 
-    fn generate(&self, values: &Values, name: &str) -> Option<Self::Output> {
-        let configmap_config = values.config_maps.get(name)?;
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(configmap_config) = values.config_maps.get(name) else {
+            return Ok(None);
+        };
 
         if !configmap_config.enabled {
-            return None;
+            return Ok(None);
         }
 
         let configmap_name = common::generate_name(values, name);
@@ -55,6 +58,6 @@ impl ResourceGenerator for ConfigMapGenerator {
             ..Default::default()
         };
 
-        Some(configmap)
+        Ok(Some(configmap))
     }
 }
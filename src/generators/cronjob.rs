@@ -0,0 +1,54 @@
+use super::{ResourceGenerator, common, pod};
+use crate::error::BunkuError;
+use crate::values::{CronJobConfig, Values};
+use k8s_openapi::api::batch::v1::{CronJob, CronJobSpec, JobSpec, JobTemplateSpec};
+
+pub struct CronJobGenerator;
+
+impl ResourceGenerator for CronJobGenerator {
+    type Output = CronJob;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(controller) = values.controllers.get(name) else {
+            return Ok(None);
+        };
+
+        if !controller.enabled || controller.r#type != "cronjob" {
+            return Ok(None);
+        }
+
+        let default_config = CronJobConfig::default();
+        let cronjob_config = controller.cronjob.as_ref().unwrap_or(&default_config);
+
+        let Some(mut template) = pod::generate_pod_template(values, name, controller)? else {
+            return Ok(None);
+        };
+        pod::default_restart_policy(&mut template, "OnFailure");
+
+        let cronjob = CronJob {
+            metadata: common::generate_controller_metadata(values, name, controller),
+            spec: Some(CronJobSpec {
+                schedule: cronjob_config.schedule.clone(),
+                time_zone: cronjob_config.time_zone.clone(),
+                suspend: cronjob_config.suspend,
+                concurrency_policy: Some(cronjob_config.concurrency_policy.clone()),
+                starting_deadline_seconds: cronjob_config.starting_deadline_seconds,
+                successful_jobs_history_limit: Some(cronjob_config.successful_jobs_history),
+                failed_jobs_history_limit: Some(cronjob_config.failed_jobs_history),
+                job_template: JobTemplateSpec {
+                    metadata: None,
+                    spec: Some(JobSpec {
+                        backoff_limit: Some(cronjob_config.backoff_limit),
+                        parallelism: cronjob_config.parallelism,
+                        ttl_seconds_after_finished: cronjob_config.ttl_seconds_after_finished,
+                        template,
+                        ..Default::default()
+                    }),
+                },
+            }),
+            ..Default::default()
+        };
+
+        Ok(Some(cronjob))
+    }
+}
@@ -0,0 +1,65 @@
+use super::{ResourceGenerator, common, pod};
+use crate::error::BunkuError;
+use crate::values::Values;
+use k8s_openapi::api::apps::v1::{
+    DaemonSet, DaemonSetSpec, DaemonSetUpdateStrategy, RollingUpdateDaemonSet,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+
+pub struct DaemonSetGenerator;
+
+impl ResourceGenerator for DaemonSetGenerator {
+    type Output = DaemonSet;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(controller) = values.controllers.get(name) else {
+            return Ok(None);
+        };
+
+        if !controller.enabled || controller.r#type != "daemonset" {
+            return Ok(None);
+        }
+
+        let selector_labels = common::generate_selector_labels(values, name);
+        let Some(template) = pod::generate_pod_template(values, name, controller)? else {
+            return Ok(None);
+        };
+
+        let update_strategy =
+            if controller.strategy.is_some() || controller.rolling_update.is_some() {
+                Some(DaemonSetUpdateStrategy {
+                    type_: Some(
+                        controller
+                            .strategy
+                            .clone()
+                            .unwrap_or_else(|| "RollingUpdate".to_string()),
+                    ),
+                    rolling_update: controller.rolling_update.as_ref().map(|ru| {
+                        RollingUpdateDaemonSet {
+                            max_surge: ru.surge.as_deref().map(common::int_or_string),
+                            max_unavailable: ru.unavailable.as_deref().map(common::int_or_string),
+                        }
+                    }),
+                })
+            } else {
+                None
+            };
+
+        let daemonset = DaemonSet {
+            metadata: common::generate_controller_metadata(values, name, controller),
+            spec: Some(DaemonSetSpec {
+                revision_history_limit: Some(controller.revision_history_limit),
+                selector: LabelSelector {
+                    match_labels: Some(selector_labels),
+                    ..Default::default()
+                },
+                update_strategy,
+                template,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        Ok(Some(daemonset))
+    }
+}
@@ -1,128 +1,256 @@
 use super::{ResourceGenerator, common};
-use crate::values::Values;
-use gateway_api::apis::v1::{HTTPRoute, HTTPRouteSpec, HTTPRouteRule, HTTPRouteMatch, HTTPPathMatch, HTTPHeaderMatch, HTTPQueryParamMatch, HTTPBackendRef, BackendRef, ParentReference};
+use crate::error::{BunkuError, Errors};
+use crate::references;
+use crate::values::{RouteBackendRef, Values};
+use gateway_api::apis::standard::httproutes::{
+    HTTPRoute, HTTPRouteParentRefs, HTTPRouteRules, HTTPRouteRulesBackendRefs,
+    HTTPRouteRulesFilters, HTTPRouteRulesMatches, HTTPRouteRulesTimeouts, HTTPRouteSpec,
+};
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use serde_json::Value;
 
 pub struct HttpRouteGenerator;
 
 impl ResourceGenerator for HttpRouteGenerator {
     type Output = HTTPRoute;
-    
-    fn generate(&self, values: &Values, name: &str) -> Option<Self::Output> {
-        let route_config = values.route.get(name)?;
-        
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(route_config) = values.route.get(name) else {
+            return Ok(None);
+        };
+
         if !route_config.enabled {
-            return None;
+            return Ok(None);
         }
-        
+
         let route_name = common::generate_name(values, name);
-        
+
         let mut labels = common::generate_labels(values, name, "httproute");
-        labels.extend(route_config.labels.iter().map(|(k, v)| (k.clone(), v.clone())));
-        
+        labels.extend(
+            route_config
+                .labels
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
         let mut annotations = common::generate_annotations(values);
-        annotations.extend(route_config.annotations.iter().map(|(k, v)| (k.clone(), v.clone())));
-        
+        annotations.extend(
+            route_config
+                .annotations
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        // Without explicit parent refs, attach to the Gateway named by
+        // className; a route with neither attaches to nothing
+        let mut errors = Errors::default();
+        let parent_refs: Vec<HTTPRouteParentRefs> = match &route_config.class_name {
+            Some(class_name) if route_config.parent_refs.is_empty() => {
+                vec![HTTPRouteParentRefs {
+                    name: class_name.clone(),
+                    ..Default::default()
+                }]
+            }
+            None if route_config.parent_refs.is_empty() => {
+                errors.push(BunkuError::invalid(
+                    format!("route.{}.parentRefs", name),
+                    "needs a Gateway to attach to, set parentRefs or className",
+                ));
+                Vec::new()
+            }
+            _ => route_config
+                .parent_refs
+                .iter()
+                .map(|parent| HTTPRouteParentRefs {
+                    name: parent.name.clone(),
+                    namespace: parent.namespace.clone(),
+                    section_name: parent.section_name.clone(),
+                    port: parent.port,
+                    group: parent.group.clone(),
+                    kind: parent.kind.clone(),
+                })
+                .collect(),
+        };
+
+        // TLS terminates on the Gateway listener, so TLS hosts only need to be
+        // matched by the route
+        let mut hostnames = route_config.hosts.clone();
+        for host in route_config.tls.iter().flat_map(|tls| tls.hosts.iter()) {
+            if !hostnames.contains(host) {
+                hostnames.push(host.clone());
+            }
+        }
+
         // Convert rules from our values format to gateway-api format
-        let rules: Vec<HTTPRouteRule> = route_config.rules.iter()
-            .filter_map(|rule| {
-                // Convert matches
-                let matches = rule.matches.iter().filter_map(|m| {
-                    let mut http_match = HTTPRouteMatch::default();
-                    
-                    // Convert path match
-                    if let Some(path) = &m.path {
-                        http_match.path = Some(HTTPPathMatch {
-                            type_: Some(path.r#type.clone()),
-                            value: Some(path.value.clone()),
-                        });
-                    }
-                    
-                    // Convert method
-                    if let Some(method) = &m.method {
-                        http_match.method = Some(method.clone());
-                    }
-                    
-                    // Convert headers
-                    if let Some(headers) = &m.headers {
-                        http_match.headers = Some(headers.iter().map(|h| {
-                            HTTPHeaderMatch {
-                                type_: Some(h.r#type.clone()),
-                                name: h.name.clone(),
-                                value: h.value.clone(),
-                            }
-                        }).collect());
-                    }
-                    
-                    // Convert query params
-                    if let Some(query_params) = &m.query_params {
-                        http_match.query_params = Some(query_params.iter().map(|q| {
-                            HTTPQueryParamMatch {
-                                type_: Some(q.r#type.clone()),
-                                name: q.name.clone(),
-                                value: q.value.clone(),
-                            }
-                        }).collect());
-                    }
-                    
-                    Some(http_match)
-                }).collect();
-                
-                // Convert backend refs
-                let backend_refs = rule.backend_refs.iter().map(|br| {
-                    HTTPBackendRef {
-                        backend_ref: Some(BackendRef {
-                            name: br.name.clone(),
-                            port: br.port,
+        let rules: Vec<HTTPRouteRules> = route_config
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(rule_index, rule)| {
+                let rule_path = format!("route.{}.rules[{}]", name, rule_index);
+
+                // Our match types serialise to the Gateway API shape
+                let matches: Vec<HTTPRouteRulesMatches> = rule
+                    .matches
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, m)| {
+                        let value = errors.capture(serde_json::to_value(m).map_err(Into::into))?;
+                        errors.capture(common::convert_value(
+                            &rule_path,
+                            &format!("matches[{}]", index),
+                            &value,
+                        ))
+                    })
+                    .collect();
+
+                let backend_refs: Vec<HTTPRouteRulesBackendRefs> = rule
+                    .backend_refs
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, br)| {
+                        let (name, port) = errors.capture(resolve_backend(
+                            values,
+                            &format!("{}.backendRefs[{}]", rule_path, index),
+                            br,
+                        ))?;
+                        Some(HTTPRouteRulesBackendRefs {
+                            name,
+                            namespace: br.namespace.clone(),
+                            port,
                             weight: br.weight,
                             ..Default::default()
-                        }),
-                        filters: None, // TODO: Implement filters if needed
-                    }
-                }).collect();
-                
-                Some(HTTPRouteRule {
-                    matches: if matches.is_empty() { None } else { Some(matches) },
-                    backend_refs: Some(backend_refs),
-                    filters: rule.filters.as_ref().and_then(|_filters| {
-                        // Convert filters from JSON values to proper types
-                        // This would need more specific implementation based on filter types
+                        })
+                    })
+                    .collect();
+
+                let filters: Vec<HTTPRouteRulesFilters> = rule
+                    .filters
+                    .iter()
+                    .flatten()
+                    .enumerate()
+                    .filter_map(|(index, filter)| {
+                        let field = format!("filters[{}]", index);
+                        let filter = errors.capture(resolve_mirror_backend(
+                            values,
+                            &format!("{}.{}.requestMirror.backendRef", rule_path, field),
+                            filter,
+                        ))?;
+                        errors.capture(common::convert_value(&rule_path, &field, &filter))
+                    })
+                    .collect();
+
+                HTTPRouteRules {
+                    matches: if matches.is_empty() {
+                        None
+                    } else {
+                        Some(matches)
+                    },
+                    backend_refs: if backend_refs.is_empty() {
                         None
+                    } else {
+                        Some(backend_refs)
+                    },
+                    filters: if filters.is_empty() {
+                        None
+                    } else {
+                        Some(filters)
+                    },
+                    timeouts: rule.timeouts.as_ref().map(|t| HTTPRouteRulesTimeouts {
+                        request: t.request.clone(),
+                        backend_request: t.backend_request.clone(),
                     }),
-                    timeouts: None,
-                    name: None,
-                })
+                }
             })
             .collect();
-        
+        errors.finish()?;
+
         let http_route = HTTPRoute {
             metadata: ObjectMeta {
                 name: Some(route_name),
                 labels: Some(labels),
-                annotations: if annotations.is_empty() { 
-                    None 
-                } else { 
-                    Some(annotations) 
+                annotations: if annotations.is_empty() {
+                    None
+                } else {
+                    Some(annotations)
                 },
                 ..Default::default()
             },
             spec: HTTPRouteSpec {
-                parent_refs: Some(vec![
-                    ParentReference {
-                        name: Some("gateway".to_string()), // Default gateway name
-                        ..Default::default()
-                    }
-                ]),
-                hostnames: if route_config.hosts.is_empty() { 
-                    None 
-                } else { 
-                    Some(route_config.hosts.clone()) 
+                parent_refs: Some(parent_refs),
+                hostnames: if hostnames.is_empty() {
+                    None
+                } else {
+                    Some(hostnames)
                 },
                 rules: if rules.is_empty() { None } else { Some(rules) },
             },
             status: None,
         };
-        
-        Some(http_route)
+
+        Ok(Some(http_route))
     }
-} 
\ No newline at end of file
+}
+
+/// Resolve a backend to a Service name and port. Bunku identifiers default to
+/// the first port of the generated Service. A rule that lost a backend would
+/// send its traffic nowhere, so an unresolved identifier is an error even
+/// when references are only warnings.
+fn resolve_backend(
+    values: &Values,
+    path: &str,
+    backend: &RouteBackendRef,
+) -> Result<(String, Option<i32>), BunkuError> {
+    match (&backend.identifier, &backend.name) {
+        (Some(identifier), _) => {
+            let service_config = values.service.get(identifier).ok_or_else(|| {
+                references::undefined(format!("{}.identifier", path), "service", identifier)
+            })?;
+            let port = backend
+                .port
+                .or_else(|| service_config.ports.values().next().map(|p| p.port))
+                .ok_or_else(|| references::no_ports(format!("{}.port", path), identifier))?;
+            Ok((common::generate_name(values, identifier), Some(port)))
+        }
+        (None, Some(name)) => Ok((name.clone(), backend.port)),
+        (None, None) => Err(BunkuError::invalid(path, "needs an identifier or a name")),
+    }
+}
+
+/// Rewrite `requestMirror.backendRef.identifier` into the generated Service
+/// name and port.
+fn resolve_mirror_backend(
+    values: &Values,
+    path: &str,
+    filter: &Value,
+) -> Result<Value, BunkuError> {
+    let mut filter = filter.clone();
+
+    if let Some(backend_ref) = filter.pointer_mut("/requestMirror/backendRef")
+        && let Some(identifier) = backend_ref
+            .get("identifier")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    {
+        let backend = RouteBackendRef {
+            identifier: Some(identifier),
+            name: None,
+            namespace: None,
+            port: backend_ref
+                .get("port")
+                .and_then(Value::as_i64)
+                .map(|p| p as i32),
+            weight: None,
+        };
+        let (name, port) = resolve_backend(values, path, &backend)?;
+        if let Some(object) = backend_ref.as_object_mut() {
+            object.remove("identifier");
+            object.insert("name".to_string(), Value::from(name));
+            if let Some(port) = port {
+                object.insert("port".to_string(), Value::from(port));
+            }
+        }
+    }
+
+    Ok(filter)
+}
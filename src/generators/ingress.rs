@@ -0,0 +1,160 @@
+use super::{ResourceGenerator, common};
+use crate::error::{BunkuError, Errors};
+use crate::references;
+use crate::values::{IngressService, Values};
+use k8s_openapi::api::networking::v1::{
+    HTTPIngressPath, HTTPIngressRuleValue, Ingress, IngressBackend, IngressRule,
+    IngressServiceBackend, IngressSpec, IngressTLS, ServiceBackendPort,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+pub struct IngressGenerator;
+
+impl ResourceGenerator for IngressGenerator {
+    type Output = Ingress;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(ingress_config) = values.ingress.get(name) else {
+            return Ok(None);
+        };
+
+        if !ingress_config.enabled {
+            return Ok(None);
+        }
+
+        let ingress_name = common::generate_name(values, name);
+
+        let mut labels = common::generate_labels(values, name, "ingress");
+        labels.extend(
+            ingress_config
+                .labels
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        let mut annotations = common::generate_annotations(values);
+        annotations.extend(
+            ingress_config
+                .annotations
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        let mut errors = Errors::default();
+        let rules: Vec<IngressRule> = ingress_config
+            .hosts
+            .iter()
+            .enumerate()
+            .map(|(host_index, host)| IngressRule {
+                host: Some(host.host.clone()),
+                http: Some(HTTPIngressRuleValue {
+                    paths: host
+                        .paths
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(path_index, path)| {
+                            let service_path = format!(
+                                "ingress.{}.hosts[{}].paths[{}].service",
+                                name, host_index, path_index
+                            );
+                            let backend = errors.capture(resolve_backend(
+                                values,
+                                &service_path,
+                                &path.service,
+                            ))?;
+                            Some(HTTPIngressPath {
+                                path: Some(path.path.clone()),
+                                path_type: path.path_type.clone(),
+                                backend: IngressBackend {
+                                    service: Some(backend),
+                                    ..Default::default()
+                                },
+                            })
+                        })
+                        .collect(),
+                }),
+            })
+            .collect();
+        errors.finish()?;
+
+        let tls: Vec<IngressTLS> = ingress_config
+            .tls
+            .iter()
+            .map(|tls| IngressTLS {
+                hosts: if tls.hosts.is_empty() {
+                    None
+                } else {
+                    Some(tls.hosts.clone())
+                },
+                // Point at bunku-managed Secrets by identifier, or any other name verbatim
+                secret_name: Some(if values.secrets.contains_key(&tls.secret_name) {
+                    common::generate_name(values, &tls.secret_name)
+                } else {
+                    tls.secret_name.clone()
+                }),
+            })
+            .collect();
+
+        let ingress = Ingress {
+            metadata: ObjectMeta {
+                name: Some(ingress_name),
+                labels: Some(labels),
+                annotations: if annotations.is_empty() {
+                    None
+                } else {
+                    Some(annotations)
+                },
+                ..Default::default()
+            },
+            spec: Some(IngressSpec {
+                ingress_class_name: ingress_config.class_name.clone(),
+                rules: if rules.is_empty() { None } else { Some(rules) },
+                tls: if tls.is_empty() { None } else { Some(tls) },
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        Ok(Some(ingress))
+    }
+}
+
+/// Resolve a path backend. An `identifier` points at a bunku Service and
+/// defaults to its first port; a `name` is used verbatim and needs a `port`.
+/// A path without a backend is invalid, so an unresolved identifier is an
+/// error even when references are only warnings.
+fn resolve_backend(
+    values: &Values,
+    path: &str,
+    service: &IngressService,
+) -> Result<IngressServiceBackend, BunkuError> {
+    let (service_name, port) = match (&service.identifier, &service.name) {
+        (Some(identifier), _) => {
+            let service_config = values.service.get(identifier).ok_or_else(|| {
+                references::undefined(format!("{}.identifier", path), "service", identifier)
+            })?;
+            let port = service
+                .port
+                .or_else(|| service_config.ports.values().next().map(|p| p.port))
+                .ok_or_else(|| references::no_ports(format!("{}.port", path), identifier))?;
+            (common::generate_name(values, identifier), port)
+        }
+        (None, Some(name)) => {
+            let port = service.port.ok_or_else(|| {
+                BunkuError::invalid(format!("{}.port", path), "required with a service name")
+            })?;
+            (name.clone(), port)
+        }
+        (None, None) => {
+            return Err(BunkuError::invalid(path, "needs an identifier or a name"));
+        }
+    };
+
+    Ok(IngressServiceBackend {
+        name: service_name,
+        port: Some(ServiceBackendPort {
+            number: Some(port),
+            ..Default::default()
+        }),
+    })
+}
@@ -0,0 +1,45 @@
+use super::{ResourceGenerator, common, pod};
+use crate::error::BunkuError;
+use crate::values::{JobConfig, Values};
+use k8s_openapi::api::batch::v1::{Job, JobSpec};
+
+pub struct JobGenerator;
+
+impl ResourceGenerator for JobGenerator {
+    type Output = Job;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(controller) = values.controllers.get(name) else {
+            return Ok(None);
+        };
+
+        if !controller.enabled || controller.r#type != "job" {
+            return Ok(None);
+        }
+
+        let default_config = JobConfig::default();
+        let job_config = controller.job.as_ref().unwrap_or(&default_config);
+
+        let Some(mut template) = pod::generate_pod_template(values, name, controller)? else {
+            return Ok(None);
+        };
+        pod::default_restart_policy(&mut template, "Never");
+
+        let job = Job {
+            metadata: common::generate_controller_metadata(values, name, controller),
+            spec: Some(JobSpec {
+                suspend: job_config.suspend,
+                ttl_seconds_after_finished: job_config.ttl_seconds_after_finished,
+                backoff_limit: Some(job_config.backoff_limit),
+                parallelism: job_config.parallelism,
+                completions: job_config.completions,
+                completion_mode: job_config.completion_mode.clone(),
+                template,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        Ok(Some(job))
+    }
+}
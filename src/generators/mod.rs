@@ -1,76 +1,258 @@
+pub mod common;
 pub mod configmap;
+pub mod cronjob;
+pub mod daemonset;
 pub mod deployment;
+pub mod http_route;
+pub mod ingress;
+pub mod job;
+pub mod namespace;
+pub mod network_policy;
+pub mod pod;
 pub mod pvc;
+pub mod raw_resource;
+pub mod rbac;
+pub mod secret;
 pub mod service;
 pub mod service_account;
-// pub mod http_route;
-pub mod common;
+pub mod service_monitor;
+pub mod statefulset;
+pub mod volume;
 
+use crate::error::{BunkuError, Errors};
 use crate::values::Values;
+use serde::Serialize;
 use serde_json::Value;
 
 pub trait ResourceGenerator {
     type Output;
 
-    fn generate(&self, values: &Values, name: &str) -> Option<Self::Output>;
+    /// Generate the resource for `name`, or `None` when it is disabled or has
+    /// nothing to render.
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError>;
+}
+
+/// Generated resources and every problem found while generating them.
+#[derive(Default)]
+struct Rendered {
+    resources: Vec<Value>,
+    errors: Errors,
+}
+
+impl Rendered {
+    fn add<T: Serialize>(&mut self, result: Result<Option<T>, BunkuError>) {
+        if let Some(Some(resource)) = self.errors.capture(result)
+            && let Some(value) = self
+                .errors
+                .capture(serde_json::to_value(resource).map_err(Into::into))
+        {
+            self.resources.push(value);
+        }
+    }
 }
 
-pub fn generate_all_resources(values: &Values) -> Vec<Value> {
-    let mut resources = Vec::new();
+pub fn generate_all_resources(values: &Values) -> Result<Vec<Value>, BunkuError> {
+    let mut rendered = Rendered::default();
+
+    // Checks that report problems where they are written, alongside whatever
+    // generation finds
+    rendered.errors.capture(pod::validate(values));
+    rendered.errors.capture(secret::validate(values));
+    rendered.errors.capture(volume::validate(values));
+    rendered.errors.capture(raw_resource::validate(values));
 
-    // Generate controllers (only deployments for now)
+    // Generate the Namespace itself
+    if let Some(namespace_name) = &values.global.namespace {
+        rendered.add(namespace::NamespaceGenerator.generate(values, namespace_name));
+    }
+
+    // Generate controllers
     for (name, controller) in &values.controllers {
-        if controller.enabled && controller.r#type == "deployment" {
-            if let Some(deployment) = deployment::DeploymentGenerator.generate(values, name) {
-                resources.push(serde_json::to_value(deployment).unwrap());
+        if !controller.enabled {
+            continue;
+        }
+        match controller.r#type.as_str() {
+            "deployment" => rendered.add(deployment::DeploymentGenerator.generate(values, name)),
+            "statefulset" => {
+                // The governing Service only makes sense next to its StatefulSet
+                let generated = statefulset::StatefulSetGenerator.generate(values, name);
+                let has_statefulset = matches!(generated, Ok(Some(_)));
+                rendered.add(generated);
+                if has_statefulset {
+                    rendered.add(Ok(statefulset::generate_headless_service(values, name)));
+                }
             }
+            "cronjob" => rendered.add(cronjob::CronJobGenerator.generate(values, name)),
+            "job" => rendered.add(job::JobGenerator.generate(values, name)),
+            "daemonset" => rendered.add(daemonset::DaemonSetGenerator.generate(values, name)),
+            _ => {}
         }
     }
 
     // Generate services
     for (name, service) in &values.service {
         if service.enabled {
-            if let Some(svc) = service::ServiceGenerator.generate(values, name) {
-                resources.push(serde_json::to_value(svc).unwrap());
-            }
+            rendered.add(service::ServiceGenerator.generate(values, name));
         }
     }
 
     // Generate ConfigMaps
     for (name, configmap) in &values.config_maps {
         if configmap.enabled {
-            if let Some(cm) = configmap::ConfigMapGenerator.generate(values, name) {
-                resources.push(serde_json::to_value(cm).unwrap());
-            }
+            rendered.add(configmap::ConfigMapGenerator.generate(values, name));
+        }
+    }
+
+    // Generate Secrets
+    for (name, secret) in &values.secrets {
+        if secret.enabled {
+            rendered.add(secret::SecretGenerator.generate(values, name));
         }
     }
 
     // Generate ServiceAccounts
     for (name, service_account) in &values.service_account {
         if service_account.enabled {
-            if let Some(sa) = service_account::ServiceAccountGenerator.generate(values, name) {
-                resources.push(serde_json::to_value(sa).unwrap());
-            }
+            rendered.add(service_account::ServiceAccountGenerator.generate(values, name));
+        }
+    }
+
+    // Generate Roles and ClusterRoles
+    for (name, role) in &values.rbac.roles {
+        if !role.enabled {
+            continue;
+        }
+        if role.cluster_wide {
+            rendered.add(rbac::ClusterRoleGenerator.generate(values, name));
+        } else {
+            rendered.add(rbac::RoleGenerator.generate(values, name));
+        }
+    }
+
+    // Generate RoleBindings and ClusterRoleBindings
+    for (name, binding) in &values.rbac.bindings {
+        if !binding.enabled {
+            continue;
+        }
+        if binding.cluster_wide {
+            rendered.add(rbac::ClusterRoleBindingGenerator.generate(values, name));
+        } else {
+            rendered.add(rbac::RoleBindingGenerator.generate(values, name));
         }
     }
 
     // Generate PVCs
     for (name, persistence) in &values.persistence {
         if persistence.enabled && persistence.r#type == "pvc" {
-            if let Some(pvc) = pvc::PvcGenerator.generate(values, name) {
-                resources.push(serde_json::to_value(pvc).unwrap());
-            }
+            rendered.add(pvc::PvcGenerator.generate(values, name));
+        }
+    }
+
+    // Generate Ingresses
+    for (name, ingress) in &values.ingress {
+        if ingress.enabled {
+            rendered.add(ingress::IngressGenerator.generate(values, name));
+        }
+    }
+
+    // Generate HTTPRoutes
+    for (name, route) in &values.route {
+        if route.enabled {
+            rendered.add(http_route::HttpRouteGenerator.generate(values, name));
+        }
+    }
+
+    // Generate NetworkPolicies
+    for (name, policy) in &values.networkpolicies {
+        if policy.enabled {
+            rendered.add(network_policy::NetworkPolicyGenerator.generate(values, name));
         }
     }
 
-    // // Generate HTTPRoutes
-    // for (name, route) in &values.route {
-    //     if route.enabled {
-    //         if let Some(http_route) = http_route::HttpRouteGenerator.generate(values, name) {
-    //             resources.push(serde_json::to_value(http_route).unwrap());
-    //         }
-    //     }
-    // }
+    // Generate ServiceMonitors
+    for (name, monitor) in &values.service_monitor {
+        if monitor.enabled {
+            rendered.add(service_monitor::ServiceMonitorGenerator.generate(values, name));
+        }
+    }
+
+    // Raw resources are verbatim apart from bunku metadata
+    for (name, raw) in &values.raw_resources {
+        if raw.enabled {
+            rendered.add(raw_resource::RawResourceGenerator.generate(values, name));
+        }
+    }
+
+    rendered.errors.finish()?;
+    let mut resources = rendered.resources;
+
+    if let Some(namespace_name) = &values.global.namespace {
+        namespace::stamp_namespace(&mut resources, namespace_name);
+    }
+    sort_for_apply(&mut resources);
+
+    Ok(resources)
+}
+
+/// Kinds in the order `kubectl apply` should see them: what others depend on
+/// comes first, workloads after their configuration, and what routes to or
+/// monitors them last. Kinds not listed follow, ordered by name.
+const APPLY_ORDER: &[&str] = &[
+    "Namespace",
+    "CustomResourceDefinition",
+    "NetworkPolicy",
+    "ResourceQuota",
+    "LimitRange",
+    "PriorityClass",
+    "ServiceAccount",
+    "ClusterRole",
+    "ClusterRoleBinding",
+    "Role",
+    "RoleBinding",
+    "ConfigMap",
+    "Secret",
+    "StorageClass",
+    "PersistentVolume",
+    "PersistentVolumeClaim",
+    "Service",
+    "DaemonSet",
+    "Deployment",
+    "StatefulSet",
+    "Job",
+    "CronJob",
+    "HorizontalPodAutoscaler",
+    "PodDisruptionBudget",
+    "IngressClass",
+    "Ingress",
+    "Gateway",
+    "HTTPRoute",
+    "GRPCRoute",
+    "ServiceMonitor",
+    "PodMonitor",
+    "PrometheusRule",
+];
 
-    resources
+/// Sort resources into apply order, then by namespace and name, so the output
+/// does not depend on the order of entries in the values files.
+fn sort_for_apply(resources: &mut [Value]) {
+    resources.sort_by_cached_key(|resource| {
+        let field = |pointer: &str| {
+            resource
+                .pointer(pointer)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let kind = field("/kind");
+        let rank = APPLY_ORDER
+            .iter()
+            .position(|known| *known == kind)
+            .unwrap_or(APPLY_ORDER.len());
+        (
+            rank,
+            kind,
+            field("/metadata/namespace"),
+            field("/metadata/name"),
+        )
+    });
 }
@@ -0,0 +1,107 @@
+use super::{ResourceGenerator, common};
+use crate::error::BunkuError;
+use crate::values::Values;
+use k8s_openapi::api::core::v1::Namespace;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Kinds that are not namespaced and must never get `metadata.namespace`.
+const CLUSTER_SCOPED_KINDS: [&str; 14] = [
+    "APIService",
+    "ClusterIssuer",
+    "ClusterRole",
+    "ClusterRoleBinding",
+    "CustomResourceDefinition",
+    "GatewayClass",
+    "IngressClass",
+    "MutatingWebhookConfiguration",
+    "Namespace",
+    "PersistentVolume",
+    "PriorityClass",
+    "RuntimeClass",
+    "StorageClass",
+    "ValidatingWebhookConfiguration",
+];
+
+/// Generates the Namespace object. Its `name` is the namespace itself, which
+/// is never release-prefixed.
+pub struct NamespaceGenerator;
+
+impl ResourceGenerator for NamespaceGenerator {
+    type Output = Namespace;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let namespace_config = &values.namespace;
+
+        if !namespace_config.create {
+            return Ok(None);
+        }
+
+        let mut labels = values
+            .global
+            .labels
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<BTreeMap<_, _>>();
+        labels.insert(
+            "app.kubernetes.io/instance".to_string(),
+            common::instance_name(values),
+        );
+        labels.extend(
+            namespace_config
+                .labels
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        let mut annotations = common::generate_annotations(values);
+        annotations.extend(
+            namespace_config
+                .annotations
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        Ok(Some(Namespace {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                labels: Some(labels),
+                annotations: if annotations.is_empty() {
+                    None
+                } else {
+                    Some(annotations)
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+    }
+}
+
+/// Set `metadata.namespace` on every namespaced resource that does not already
+/// name one. An empty namespace marks a cluster-scoped kind this list does not
+/// know, like a custom resource, and is removed instead.
+pub fn stamp_namespace(resources: &mut [Value], namespace: &str) {
+    for resource in resources {
+        let cluster_scoped = resource["kind"]
+            .as_str()
+            .is_some_and(|kind| CLUSTER_SCOPED_KINDS.contains(&kind));
+        if cluster_scoped {
+            continue;
+        }
+
+        let Some(metadata) = resource.get_mut("metadata").and_then(Value::as_object_mut) else {
+            continue;
+        };
+        match metadata.get("namespace") {
+            None => {
+                metadata.insert("namespace".to_string(), Value::from(namespace));
+            }
+            Some(Value::String(existing)) if existing.is_empty() => {
+                metadata.remove("namespace");
+            }
+            Some(_) => {}
+        }
+    }
+}
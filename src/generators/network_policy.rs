@@ -0,0 +1,174 @@
+use super::{ResourceGenerator, common};
+use crate::error::{BunkuError, Errors};
+use crate::values::Values;
+use k8s_openapi::api::networking::v1::{
+    NetworkPolicy, NetworkPolicyEgressRule, NetworkPolicyIngressRule, NetworkPolicyPeer,
+    NetworkPolicySpec,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta};
+use serde_json::Value;
+
+pub struct NetworkPolicyGenerator;
+
+impl ResourceGenerator for NetworkPolicyGenerator {
+    type Output = NetworkPolicy;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(policy_config) = values.networkpolicies.get(name) else {
+            return Ok(None);
+        };
+
+        if !policy_config.enabled {
+            return Ok(None);
+        }
+
+        let policy_name = common::generate_name(values, name);
+
+        let mut labels = common::generate_labels(values, name, "networkpolicy");
+        labels.extend(
+            policy_config
+                .labels
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        let mut annotations = common::generate_annotations(values);
+        annotations.extend(
+            policy_config
+                .annotations
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        let path = format!("networkpolicies.{}", name);
+        let mut errors = Errors::default();
+
+        let pod_selector = match (&policy_config.controller, &policy_config.pod_selector) {
+            (Some(_), Some(_)) => {
+                errors.push(BunkuError::invalid(
+                    format!("{}.podSelector", path),
+                    "conflicts with controller, set only one of them",
+                ));
+                LabelSelector::default()
+            }
+            (Some(controller), None) => LabelSelector {
+                match_labels: Some(common::generate_selector_labels(values, controller)),
+                ..Default::default()
+            },
+            (None, Some(selector)) => errors
+                .capture(common::convert_value(&path, "podSelector", selector))
+                .unwrap_or_default(),
+            (None, None) => LabelSelector::default(),
+        };
+
+        let ingress: Vec<NetworkPolicyIngressRule> = policy_config
+            .ingress
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                let rule_path = format!("{}.ingress[{}]", path, index);
+                NetworkPolicyIngressRule {
+                    from: errors
+                        .capture(convert_peers(values, &rule_path, "from", &rule.from))
+                        .flatten(),
+                    ports: errors
+                        .capture(common::convert_list(&rule_path, "ports", Some(&rule.ports)))
+                        .flatten(),
+                }
+            })
+            .collect();
+
+        let egress: Vec<NetworkPolicyEgressRule> = policy_config
+            .egress
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                let rule_path = format!("{}.egress[{}]", path, index);
+                NetworkPolicyEgressRule {
+                    to: errors
+                        .capture(convert_peers(values, &rule_path, "to", &rule.to))
+                        .flatten(),
+                    ports: errors
+                        .capture(common::convert_list(&rule_path, "ports", Some(&rule.ports)))
+                        .flatten(),
+                }
+            })
+            .collect();
+
+        errors.finish()?;
+
+        let network_policy = NetworkPolicy {
+            metadata: ObjectMeta {
+                name: Some(policy_name),
+                labels: Some(labels),
+                annotations: if annotations.is_empty() {
+                    None
+                } else {
+                    Some(annotations)
+                },
+                ..Default::default()
+            },
+            spec: Some(NetworkPolicySpec {
+                pod_selector,
+                policy_types: if policy_config.policy_types.is_empty() {
+                    None
+                } else {
+                    Some(policy_config.policy_types.clone())
+                },
+                ingress: if ingress.is_empty() {
+                    None
+                } else {
+                    Some(ingress)
+                },
+                egress: if egress.is_empty() {
+                    None
+                } else {
+                    Some(egress)
+                },
+            }),
+        };
+
+        Ok(Some(network_policy))
+    }
+}
+
+/// Convert rule peers, expanding the `{ controller = "web" }` shorthand into a
+/// podSelector matching that controller's pods.
+fn convert_peers(
+    values: &Values,
+    path: &str,
+    field: &str,
+    peers: &[Value],
+) -> Result<Option<Vec<NetworkPolicyPeer>>, BunkuError> {
+    let mut errors = Errors::default();
+    let peers: Vec<Value> = peers
+        .iter()
+        .enumerate()
+        .map(|(index, peer)| {
+            let mut peer = peer.clone();
+            if let Some(object) = peer.as_object_mut()
+                && let Some(controller) = object.remove("controller")
+            {
+                match controller.as_str() {
+                    Some(controller) => {
+                        object.insert(
+                            "podSelector".to_string(),
+                            serde_json::json!({
+                                "matchLabels": common::generate_selector_labels(values, controller),
+                            }),
+                        );
+                    }
+                    None => errors.push(BunkuError::invalid(
+                        format!("{}.{}[{}].controller", path, field, index),
+                        "expected a controller name",
+                    )),
+                }
+            }
+            peer
+        })
+        .collect();
+
+    let converted = errors.capture(common::convert_list(path, field, Some(&peers)));
+    errors.finish()?;
+    Ok(converted.flatten())
+}
@@ -0,0 +1,449 @@
+use super::common::{convert_list, convert_optional};
+use super::{common, volume};
+use crate::error::{BunkuError, Errors};
+use crate::values::{Container as ValueContainer, Controller, PodOptions, Values};
+use indexmap::IndexMap;
+use k8s_openapi::api::core::v1::{
+    Container, ContainerPort, EnvVar, EnvVarSource, LocalObjectReference, PodSpec, PodTemplateSpec,
+    ResourceRequirements, VolumeMount as K8sVolumeMount,
+};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use std::collections::BTreeMap;
+
+/// Build the pod template shared by every controller type.
+///
+/// Returns `None` when the controller has no containers.
+pub fn generate_pod_template(
+    values: &Values,
+    name: &str,
+    controller: &Controller,
+) -> Result<Option<PodTemplateSpec>, BunkuError> {
+    let selector_labels = common::generate_selector_labels(values, name);
+
+    // Merge pod options
+    let pod_options = common::merge_pod_options(
+        &values.default_pod_options,
+        &controller.pod_options,
+        &values.default_pod_options_strategy,
+        &values.default_pod_options_list_strategy,
+    )?;
+
+    let mut pod_labels = selector_labels;
+    pod_labels.extend(
+        pod_options
+            .labels
+            .iter()
+            .flatten()
+            .map(|(k, v)| (k.clone(), v.clone())),
+    );
+    if values.global.propagate_global_metadata_to_pods {
+        pod_labels.extend(
+            values
+                .global
+                .labels
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+    }
+
+    let mut pod_annotations: BTreeMap<String, String> = pod_options
+        .annotations
+        .iter()
+        .flatten()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    if values.global.propagate_global_metadata_to_pods {
+        pod_annotations.extend(
+            values
+                .global
+                .annotations
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+    }
+
+    // Convert containers and init containers, reporting problems in both
+    let mut errors = Errors::default();
+    let containers = errors.capture(convert_containers(
+        values,
+        name,
+        "containers",
+        &controller.containers,
+    ));
+    let init_containers = errors.capture(convert_containers(
+        values,
+        name,
+        "initContainers",
+        &controller.init_containers,
+    ));
+
+    let volumes = errors.capture(volume::generate_volumes(values, name, controller));
+    if let Some(volumes) = &volumes {
+        errors.capture(volume::check_mounts(values, name, controller, volumes));
+    }
+    let volumes = volumes.unwrap_or_default();
+
+    let mut spec = PodSpec {
+        containers: containers.unwrap_or_default(),
+        init_containers: init_containers.filter(|init_containers| !init_containers.is_empty()),
+        service_account_name: controller.service_account.as_ref().and_then(|sa| {
+            sa.name.clone().or_else(|| {
+                sa.identifier
+                    .as_ref()
+                    .map(|identifier| common::generate_name(values, identifier))
+            })
+        }),
+        volumes: if volumes.is_empty() {
+            None
+        } else {
+            Some(volumes)
+        },
+        ..Default::default()
+    };
+    // Problems are reported at the options a field came from, so those
+    // `validate` found already are listed once and only what the merge
+    // produced is new
+    let controller_path = format!("controllers.{}.podOptions", name);
+    let defaults = serde_json::to_value(&values.default_pod_options)?;
+    let own = serde_json::to_value(&controller.pod_options)?;
+    let is_set = |options: &serde_json::Value, field: &str| {
+        options.get(field).is_some_and(|value| !value.is_null())
+    };
+    errors.capture(apply_pod_options(
+        values,
+        &mut spec,
+        &pod_options,
+        &|field| {
+            if is_set(&defaults, field) && !is_set(&own, field) {
+                "defaultPodOptions".to_string()
+            } else {
+                controller_path.clone()
+            }
+        },
+    ));
+    errors.finish()?;
+
+    if spec.containers.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(PodTemplateSpec {
+        metadata: Some(ObjectMeta {
+            labels: Some(pod_labels),
+            annotations: if pod_annotations.is_empty() {
+                None
+            } else {
+                Some(pod_annotations)
+            },
+            ..Default::default()
+        }),
+        spec: Some(spec),
+    }))
+}
+
+/// Copy pod options into the pod spec, deserialising the untyped fields into
+/// their k8s-openapi types. `path` gives the path of the options that set a
+/// field.
+fn apply_pod_options(
+    values: &Values,
+    spec: &mut PodSpec,
+    pod_options: &PodOptions,
+    path: &dyn Fn(&str) -> String,
+) -> Result<(), BunkuError> {
+    let node_selector: BTreeMap<String, String> = pod_options
+        .node_selector
+        .iter()
+        .flatten()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+
+    // Point at bunku-managed Secrets by identifier, or any other name verbatim
+    let image_pull_secrets: Vec<LocalObjectReference> = pod_options
+        .image_pull_secrets
+        .iter()
+        .flatten()
+        .map(|secret| LocalObjectReference {
+            name: if values.secrets.contains_key(secret) {
+                common::generate_name(values, secret)
+            } else {
+                secret.clone()
+            },
+        })
+        .collect();
+
+    let mut errors = Errors::default();
+    spec.affinity = errors
+        .capture(convert_optional(
+            &path("affinity"),
+            "affinity",
+            &pod_options.affinity,
+        ))
+        .flatten();
+    spec.automount_service_account_token =
+        Some(pod_options.automount_service_account_token.unwrap_or(false));
+    spec.dns_config = errors
+        .capture(convert_optional(
+            &path("dnsConfig"),
+            "dnsConfig",
+            &pod_options.dns_config,
+        ))
+        .flatten();
+    spec.dns_policy = pod_options.dns_policy.clone();
+    spec.enable_service_links = Some(pod_options.enable_service_links.unwrap_or(false));
+    spec.hostname = pod_options.hostname.clone();
+    spec.host_aliases = errors
+        .capture(convert_list(
+            &path("hostAliases"),
+            "hostAliases",
+            pod_options.host_aliases.as_deref(),
+        ))
+        .flatten();
+    spec.host_ipc = Some(pod_options.host_ipc.unwrap_or(false));
+    spec.host_network = Some(pod_options.host_network.unwrap_or(false));
+    spec.host_pid = Some(pod_options.host_pid.unwrap_or(false));
+    spec.host_users = pod_options.host_users;
+    spec.image_pull_secrets = if image_pull_secrets.is_empty() {
+        None
+    } else {
+        Some(image_pull_secrets)
+    };
+    spec.node_selector = if node_selector.is_empty() {
+        None
+    } else {
+        Some(node_selector)
+    };
+    spec.priority_class_name = pod_options.priority_class_name.clone();
+    spec.restart_policy = pod_options.restart_policy.clone();
+    spec.runtime_class_name = pod_options.runtime_class_name.clone();
+    spec.scheduler_name = pod_options.scheduler_name.clone();
+    spec.security_context = errors
+        .capture(convert_optional(
+            &path("securityContext"),
+            "securityContext",
+            &pod_options.security_context,
+        ))
+        .flatten();
+    spec.share_process_namespace = pod_options.share_process_namespace;
+    spec.termination_grace_period_seconds = pod_options.termination_grace_period_seconds;
+    spec.tolerations = errors
+        .capture(convert_list(
+            &path("tolerations"),
+            "tolerations",
+            pod_options.tolerations.as_deref(),
+        ))
+        .flatten();
+    spec.topology_spread_constraints = errors
+        .capture(convert_list(
+            &path("topologySpreadConstraints"),
+            "topologySpreadConstraints",
+            pod_options.topology_spread_constraints.as_deref(),
+        ))
+        .flatten();
+
+    errors.finish()
+}
+
+/// Check the default pod options and every enabled controller's own pod
+/// options on their own, so problems are reported where they are written
+/// rather than in the merged result.
+pub fn validate(values: &Values) -> Result<(), BunkuError> {
+    let mut errors = Errors::default();
+    errors.capture(apply_pod_options(
+        values,
+        &mut PodSpec::default(),
+        &values.default_pod_options,
+        &|_| "defaultPodOptions".to_string(),
+    ));
+
+    for (name, controller) in &values.controllers {
+        if !controller.enabled {
+            continue;
+        }
+
+        errors.capture(apply_pod_options(
+            values,
+            &mut PodSpec::default(),
+            &controller.pod_options,
+            &|_| format!("controllers.{}.podOptions", name),
+        ));
+    }
+
+    errors.capture(common::merge_pod_options(
+        &values.default_pod_options,
+        &PodOptions::default(),
+        &values.default_pod_options_strategy,
+        &values.default_pod_options_list_strategy,
+    ));
+
+    errors.finish()
+}
+
+fn convert_containers(
+    values: &Values,
+    controller_name: &str,
+    field: &str,
+    containers: &IndexMap<String, ValueContainer>,
+) -> Result<Vec<Container>, BunkuError> {
+    let mut errors = Errors::default();
+    let converted = containers
+        .iter()
+        .filter_map(|(container_name, container_config)| {
+            let path = format!(
+                "controllers.{}.{}.{}",
+                controller_name, field, container_name
+            );
+            let mut container = errors.capture(convert_container(
+                values,
+                &path,
+                container_name,
+                container_config,
+            ))?;
+            volume::add_global_mounts(values, controller_name, &mut container);
+            Some(container)
+        })
+        .collect();
+    errors.finish()?;
+    Ok(converted)
+}
+
+/// Set the pod restart policy unless the user configured one explicitly.
+pub fn default_restart_policy(template: &mut PodTemplateSpec, restart_policy: &str) {
+    if let Some(spec) = template.spec.as_mut()
+        && spec.restart_policy.is_none()
+    {
+        spec.restart_policy = Some(restart_policy.to_string());
+    }
+}
+
+pub fn convert_container(
+    values: &Values,
+    path: &str,
+    name: &str,
+    config: &ValueContainer,
+) -> Result<Container, BunkuError> {
+    let mut errors = Errors::default();
+    let env: Vec<EnvVar> = config
+        .env
+        .iter()
+        .filter_map(|(key, env_var)| {
+            let mut value_from: Option<EnvVarSource> = errors.capture(convert_optional(
+                path,
+                &format!("env.{}.valueFrom", key),
+                &env_var.value_from,
+            ))?;
+
+            // Key references to bunku-managed ConfigMaps and Secrets follow their
+            // generated names
+            if let Some(source) = value_from.as_mut() {
+                if let Some(key_ref) = source.config_map_key_ref.as_mut()
+                    && values.config_maps.contains_key(&key_ref.name)
+                {
+                    key_ref.name = common::generate_name(values, &key_ref.name);
+                }
+                if let Some(key_ref) = source.secret_key_ref.as_mut()
+                    && values.secrets.contains_key(&key_ref.name)
+                {
+                    key_ref.name = common::generate_name(values, &key_ref.name);
+                }
+            }
+
+            Some(EnvVar {
+                name: key.clone(),
+                value: env_var.value.clone(),
+                value_from,
+            })
+        })
+        .collect();
+
+    let security_context = errors
+        .capture(convert_optional(
+            path,
+            "securityContext",
+            &config.security_context,
+        ))
+        .flatten();
+    let liveness_probe = errors
+        .capture(convert_optional(
+            path,
+            "livenessProbe",
+            &config.liveness_probe,
+        ))
+        .flatten();
+    let readiness_probe = errors
+        .capture(convert_optional(
+            path,
+            "readinessProbe",
+            &config.readiness_probe,
+        ))
+        .flatten();
+    let startup_probe = errors
+        .capture(convert_optional(
+            path,
+            "startupProbe",
+            &config.startup_probe,
+        ))
+        .flatten();
+    errors.finish()?;
+
+    Ok(Container {
+        name: name.to_string(),
+        image: Some(config.image.clone()),
+        command: config.command.clone(),
+        args: config.args.clone(),
+        env: if env.is_empty() { None } else { Some(env) },
+        ports: if config.ports.is_empty() {
+            None
+        } else {
+            Some(
+                config
+                    .ports
+                    .iter()
+                    .map(|(port_name, port_config)| ContainerPort {
+                        name: Some(port_name.clone()),
+                        container_port: port_config.container_port,
+                        protocol: port_config.protocol.clone(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            )
+        },
+        resources: config.resources.as_ref().map(|res| ResourceRequirements {
+            limits: res.limits.as_ref().map(|limits| {
+                limits
+                    .iter()
+                    .map(|(k, v)| (k.clone(), Quantity(v.clone())))
+                    .collect()
+            }),
+            requests: res.requests.as_ref().map(|requests| {
+                requests
+                    .iter()
+                    .map(|(k, v)| (k.clone(), Quantity(v.clone())))
+                    .collect()
+            }),
+            ..Default::default()
+        }),
+        security_context,
+        volume_mounts: if config.volume_mounts.is_empty() {
+            None
+        } else {
+            Some(
+                config
+                    .volume_mounts
+                    .iter()
+                    .map(|vm| K8sVolumeMount {
+                        name: vm.name.clone(),
+                        mount_path: vm.mount_path.clone(),
+                        read_only: vm.read_only,
+                        sub_path: vm.sub_path.clone(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            )
+        },
+        liveness_probe,
+        readiness_probe,
+        startup_probe,
+        ..Default::default()
+    })
+}
@@ -1,5 +1,6 @@
 use super::{ResourceGenerator, common};
-use crate::values::Values;
+use crate::error::{BunkuError, Errors};
+use crate::values::{PersistenceItem, Values};
 use k8s_openapi::api::core::v1::{
     PersistentVolumeClaim, PersistentVolumeClaimSpec, VolumeResourceRequirements,
 };
@@ -12,11 +13,13 @@ pub struct PvcGenerator;
 impl ResourceGenerator for PvcGenerator {
     type Output = PersistentVolumeClaim;
 
-    fn generate(&self, values: &Values, name: &str) -> Option<Self::Output> {
-        let persistence_config = values.persistence.get(name)?;
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(persistence_config) = values.persistence.get(name) else {
+            return Ok(None);
+        };
 
         if !persistence_config.enabled || persistence_config.r#type != "pvc" {
-            return None;
+            return Ok(None);
         }
 
         let pvc_name = common::generate_name(values, name);
@@ -37,13 +40,6 @@ impl ResourceGenerator for PvcGenerator {
                 .map(|(k, v)| (k.clone(), v.clone())),
         );
 
-        let mut resources = VolumeResourceRequirements::default();
-        if let Some(size) = &persistence_config.size {
-            let mut requests = BTreeMap::new();
-            requests.insert("storage".to_string(), Quantity(size.clone()));
-            resources.requests = Some(requests);
-        }
-
         let pvc = PersistentVolumeClaim {
             metadata: ObjectMeta {
                 name: Some(pvc_name),
@@ -55,27 +51,57 @@ impl ResourceGenerator for PvcGenerator {
                 },
                 ..Default::default()
             },
-            spec: Some(PersistentVolumeClaimSpec {
-                access_modes: if persistence_config.access_modes.is_empty() {
-                    Some(vec!["ReadWriteOnce".to_string()])
-                } else {
-                    Some(persistence_config.access_modes.clone())
-                },
-                resources: Some(resources),
-                storage_class_name: persistence_config.storage_class.clone(),
-                data_source: persistence_config
-                    .data_source
-                    .as_ref()
-                    .and_then(|ds| serde_json::from_value(ds.clone()).ok()),
-                data_source_ref: persistence_config
-                    .data_source_ref
-                    .as_ref()
-                    .and_then(|dsr| serde_json::from_value(dsr.clone()).ok()),
-                ..Default::default()
-            }),
+            spec: Some(generate_pvc_spec(
+                persistence_config,
+                &format!("persistence.{}", name),
+            )?),
             ..Default::default()
         };
 
-        Some(pvc)
+        Ok(Some(pvc))
     }
 }
+
+/// Build a claim spec from a persistence entry. Shared with StatefulSet
+/// `volumeClaimTemplates` and ephemeral volumes.
+pub fn generate_pvc_spec(
+    persistence_config: &PersistenceItem,
+    path: &str,
+) -> Result<PersistentVolumeClaimSpec, BunkuError> {
+    let mut resources = VolumeResourceRequirements::default();
+    if let Some(size) = &persistence_config.size {
+        let mut requests = BTreeMap::new();
+        requests.insert("storage".to_string(), Quantity(size.clone()));
+        resources.requests = Some(requests);
+    }
+
+    let mut errors = Errors::default();
+    let data_source = errors
+        .capture(common::convert_optional(
+            path,
+            "dataSource",
+            &persistence_config.data_source,
+        ))
+        .flatten();
+    let data_source_ref = errors
+        .capture(common::convert_optional(
+            path,
+            "dataSourceRef",
+            &persistence_config.data_source_ref,
+        ))
+        .flatten();
+    errors.finish()?;
+
+    Ok(PersistentVolumeClaimSpec {
+        access_modes: if persistence_config.access_modes.is_empty() {
+            Some(vec!["ReadWriteOnce".to_string()])
+        } else {
+            Some(persistence_config.access_modes.clone())
+        },
+        resources: Some(resources),
+        storage_class_name: persistence_config.storage_class.clone(),
+        data_source,
+        data_source_ref,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,156 @@
+use super::{ResourceGenerator, common, namespace};
+use crate::error::{BunkuError, Errors};
+use crate::values::Values;
+use serde_json::{Map, Value};
+
+/// Kinds whose names the API server dictates, such as `<plural>.<group>` for a
+/// CustomResourceDefinition, so they never get the release prefix.
+const FIXED_NAME_KINDS: [&str; 3] = ["APIService", "CustomResourceDefinition", "Namespace"];
+
+pub struct RawResourceGenerator;
+
+impl ResourceGenerator for RawResourceGenerator {
+    type Output = Value;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(raw_config) = values.raw_resources.get(name) else {
+            return Ok(None);
+        };
+
+        if !raw_config.enabled {
+            return Ok(None);
+        }
+
+        let mut resource = raw_config.spec.clone();
+        let fixed_name = resource
+            .get("kind")
+            .and_then(Value::as_str)
+            .is_some_and(|kind| FIXED_NAME_KINDS.contains(&kind));
+        let missing_name = || {
+            BunkuError::invalid(
+                format!("rawResources.{}.spec", name),
+                "missing metadata.name",
+            )
+        };
+        let metadata = resource
+            .get_mut("metadata")
+            .and_then(Value::as_object_mut)
+            .ok_or_else(missing_name)?;
+
+        let resource_name = metadata
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(missing_name)?
+            .to_string();
+        if !fixed_name {
+            metadata.insert(
+                "name".to_string(),
+                Value::from(common::generate_name(values, &resource_name)),
+            );
+        }
+
+        // The resource's own labels and annotations win over global ones
+        merge_into(metadata, "labels", &values.global.labels);
+        merge_into(
+            metadata,
+            "annotations",
+            &common::generate_annotations(values),
+        );
+
+        Ok(Some(resource))
+    }
+}
+
+fn merge_into<'a>(
+    metadata: &mut Map<String, Value>,
+    field: &str,
+    global: impl IntoIterator<Item = (&'a String, &'a String)>,
+) {
+    let mut merged: Map<String, Value> = global
+        .into_iter()
+        .map(|(k, v)| (k.clone(), Value::from(v.clone())))
+        .collect();
+    if merged.is_empty() {
+        return;
+    }
+
+    if let Some(Value::Object(existing)) = metadata.get(field) {
+        merged.extend(existing.clone());
+    }
+    metadata.insert(field.to_string(), Value::Object(merged));
+}
+
+/// Check every enabled raw resource has `apiVersion`, `kind` and
+/// `metadata.name`.
+pub fn validate(values: &Values) -> Result<(), BunkuError> {
+    let mut errors = Errors::default();
+    for (name, raw_config) in &values.raw_resources {
+        if !raw_config.enabled {
+            continue;
+        }
+
+        for pointer in ["/apiVersion", "/kind", "/metadata/name"] {
+            let present = raw_config
+                .spec
+                .pointer(pointer)
+                .and_then(Value::as_str)
+                .is_some_and(|value| !value.is_empty());
+            if !present {
+                errors.push(BunkuError::invalid(
+                    format!("rawResources.{}.spec", name),
+                    format!("missing {}", pointer[1..].replace('/', ".")),
+                ));
+            }
+        }
+    }
+
+    errors.finish()
+}
+
+/// Reject raw resources that collide with another rendered resource of the
+/// same API group, kind, namespace and name.
+pub fn validate_unique(values: &Values, resources: &[Value]) -> Result<(), BunkuError> {
+    let mut errors = Errors::default();
+    for (name, raw_config) in &values.raw_resources {
+        if !raw_config.enabled {
+            continue;
+        }
+
+        let Some(mut generated) = RawResourceGenerator.generate(values, name)? else {
+            continue;
+        };
+        // Compare with the namespace the rendered resources were given
+        if let Some(namespace_name) = &values.global.namespace {
+            namespace::stamp_namespace(std::slice::from_mut(&mut generated), namespace_name);
+        }
+        let id = identity(&generated);
+        let count = resources.iter().filter(|r| identity(r) == id).count();
+        if count > 1 {
+            errors.push(BunkuError::invalid(
+                format!("rawResources.{}", name),
+                format!("{} {:?} is already generated", id.1, id.3),
+            ));
+        }
+    }
+
+    errors.finish()
+}
+
+fn identity(resource: &Value) -> (&str, &str, &str, &str) {
+    let field = |pointer: &str| {
+        resource
+            .pointer(pointer)
+            .and_then(Value::as_str)
+            .unwrap_or("")
+    };
+    // Only the group matters, the same object may be served by several versions
+    let group = field("/apiVersion")
+        .rsplit_once('/')
+        .map_or("", |(group, _)| group);
+    (
+        group,
+        field("/kind"),
+        field("/metadata/namespace"),
+        field("/metadata/name"),
+    )
+}
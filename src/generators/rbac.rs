@@ -0,0 +1,259 @@
+use super::{ResourceGenerator, common};
+use crate::error::{BunkuError, Errors};
+use crate::references;
+use crate::values::{PolicyRule as ValuePolicyRule, RoleBinding as ValueRoleBinding, Values};
+use k8s_openapi::api::rbac::v1::{
+    ClusterRole, ClusterRoleBinding, PolicyRule, Role, RoleBinding, RoleRef, Subject,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use std::collections::BTreeMap;
+
+pub struct RoleGenerator;
+pub struct ClusterRoleGenerator;
+pub struct RoleBindingGenerator;
+pub struct ClusterRoleBindingGenerator;
+
+impl ResourceGenerator for RoleGenerator {
+    type Output = Role;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(role_config) = values.rbac.roles.get(name) else {
+            return Ok(None);
+        };
+
+        if !role_config.enabled || role_config.cluster_wide {
+            return Ok(None);
+        }
+
+        Ok(Some(Role {
+            metadata: generate_metadata(
+                values,
+                name,
+                "role",
+                &role_config.labels,
+                &role_config.annotations,
+            ),
+            rules: convert_rules(&role_config.rules),
+        }))
+    }
+}
+
+impl ResourceGenerator for ClusterRoleGenerator {
+    type Output = ClusterRole;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(role_config) = values.rbac.roles.get(name) else {
+            return Ok(None);
+        };
+
+        if !role_config.enabled || !role_config.cluster_wide {
+            return Ok(None);
+        }
+
+        Ok(Some(ClusterRole {
+            metadata: generate_metadata(
+                values,
+                name,
+                "clusterrole",
+                &role_config.labels,
+                &role_config.annotations,
+            ),
+            rules: convert_rules(&role_config.rules),
+            ..Default::default()
+        }))
+    }
+}
+
+impl ResourceGenerator for RoleBindingGenerator {
+    type Output = RoleBinding;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(binding_config) = values.rbac.bindings.get(name) else {
+            return Ok(None);
+        };
+
+        if !binding_config.enabled || binding_config.cluster_wide {
+            return Ok(None);
+        }
+
+        Ok(Some(RoleBinding {
+            metadata: generate_metadata(
+                values,
+                name,
+                "rolebinding",
+                &binding_config.labels,
+                &binding_config.annotations,
+            ),
+            role_ref: convert_role_ref(values, binding_config),
+            subjects: convert_subjects(values, name, binding_config)?,
+        }))
+    }
+}
+
+impl ResourceGenerator for ClusterRoleBindingGenerator {
+    type Output = ClusterRoleBinding;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(binding_config) = values.rbac.bindings.get(name) else {
+            return Ok(None);
+        };
+
+        if !binding_config.enabled || !binding_config.cluster_wide {
+            return Ok(None);
+        }
+
+        Ok(Some(ClusterRoleBinding {
+            metadata: generate_metadata(
+                values,
+                name,
+                "clusterrolebinding",
+                &binding_config.labels,
+                &binding_config.annotations,
+            ),
+            role_ref: convert_role_ref(values, binding_config),
+            subjects: convert_subjects(values, name, binding_config)?,
+        }))
+    }
+}
+
+fn generate_metadata(
+    values: &Values,
+    name: &str,
+    resource_type: &str,
+    extra_labels: &BTreeMap<String, String>,
+    extra_annotations: &BTreeMap<String, String>,
+) -> ObjectMeta {
+    let mut labels = common::generate_labels(values, name, resource_type);
+    labels.extend(extra_labels.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+    let mut annotations = common::generate_annotations(values);
+    annotations.extend(
+        extra_annotations
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone())),
+    );
+
+    ObjectMeta {
+        name: Some(common::generate_name(values, name)),
+        labels: Some(labels),
+        annotations: if annotations.is_empty() {
+            None
+        } else {
+            Some(annotations)
+        },
+        ..Default::default()
+    }
+}
+
+fn convert_rules(rules: &[ValuePolicyRule]) -> Option<Vec<PolicyRule>> {
+    if rules.is_empty() {
+        return None;
+    }
+
+    let non_empty = |items: &Vec<String>| {
+        if items.is_empty() {
+            None
+        } else {
+            Some(items.clone())
+        }
+    };
+
+    Some(
+        rules
+            .iter()
+            .map(|rule| PolicyRule {
+                api_groups: non_empty(&rule.api_groups),
+                resources: non_empty(&rule.resources),
+                resource_names: non_empty(&rule.resource_names),
+                verbs: rule.verbs.clone(),
+                ..Default::default()
+            })
+            .collect(),
+    )
+}
+
+/// Point at a bunku role by its key, or any other role verbatim.
+fn convert_role_ref(values: &Values, binding_config: &ValueRoleBinding) -> RoleRef {
+    let role_ref = &binding_config.role_ref;
+
+    RoleRef {
+        api_group: role_ref.api_group.clone(),
+        kind: role_ref.kind.clone(),
+        name: if values.rbac.roles.contains_key(&role_ref.name) {
+            common::generate_name(values, &role_ref.name)
+        } else {
+            role_ref.name.clone()
+        },
+    }
+}
+
+/// Resolve subjects. A `ServiceAccount` subject with an `identifier` gets the
+/// generated ServiceAccount name and the release namespace.
+fn convert_subjects(
+    values: &Values,
+    name: &str,
+    binding_config: &ValueRoleBinding,
+) -> Result<Option<Vec<Subject>>, BunkuError> {
+    if binding_config.subjects.is_empty() {
+        return Ok(None);
+    }
+
+    let mut errors = Errors::default();
+    let subjects = binding_config
+        .subjects
+        .iter()
+        .enumerate()
+        .filter_map(|(index, subject)| {
+            let path = format!("rbac.bindings.{}.subjects[{}]", name, index);
+            let name = match (&subject.identifier, &subject.name) {
+                (Some(identifier), _) => {
+                    // A missing subject would silently narrow the binding
+                    if !values.service_account.contains_key(identifier) {
+                        errors.push(references::undefined(
+                            format!("{}.identifier", path),
+                            "serviceAccount",
+                            identifier,
+                        ));
+                        return None;
+                    }
+                    common::generate_name(values, identifier)
+                }
+                (None, Some(name)) => name.clone(),
+                (None, None) => {
+                    errors.push(BunkuError::invalid(path, "needs an identifier or a name"));
+                    return None;
+                }
+            };
+
+            // Bunku ServiceAccounts live in the release namespace
+            let namespace = subject.namespace.clone().or_else(|| {
+                subject
+                    .identifier
+                    .as_ref()
+                    .and(values.global.namespace.clone())
+            });
+            // Only a RoleBinding has a namespace of its own to fall back to
+            if binding_config.cluster_wide
+                && subject.kind == "ServiceAccount"
+                && namespace.is_none()
+            {
+                errors.push(BunkuError::invalid(
+                    format!("{}.namespace", path),
+                    "a ServiceAccount subject of a cluster-wide binding needs a namespace, \
+                     set it here, in global.namespace or with --namespace",
+                ));
+                return None;
+            }
+
+            Some(Subject {
+                api_group: subject.api_group.clone(),
+                kind: subject.kind.clone(),
+                name,
+                namespace,
+            })
+        })
+        .collect();
+    errors.finish()?;
+
+    Ok(Some(subjects))
+}
@@ -0,0 +1,166 @@
+use super::{ResourceGenerator, common};
+use crate::error::{BunkuError, Errors};
+use crate::values::{Secret as SecretConfig, Values};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use k8s_openapi::ByteString;
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use std::collections::BTreeMap;
+
+pub struct SecretGenerator;
+
+impl ResourceGenerator for SecretGenerator {
+    type Output = Secret;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(secret_config) = values.secrets.get(name) else {
+            return Ok(None);
+        };
+
+        if !secret_config.enabled {
+            return Ok(None);
+        }
+
+        let secret_name = common::generate_name(values, name);
+
+        let mut labels = common::generate_labels(values, name, "secret");
+        labels.extend(
+            secret_config
+                .labels
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        let mut annotations = common::generate_annotations(values);
+        annotations.extend(
+            secret_config
+                .annotations
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        let data = secret_data(name, secret_config)?;
+
+        let string_data: BTreeMap<String, String> = secret_config
+            .string_data
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        let secret = Secret {
+            metadata: ObjectMeta {
+                name: Some(secret_name),
+                labels: Some(labels),
+                annotations: if annotations.is_empty() {
+                    None
+                } else {
+                    Some(annotations)
+                },
+                ..Default::default()
+            },
+            type_: Some(secret_config.r#type.clone()),
+            data: if data.is_empty() { None } else { Some(data) },
+            string_data: if string_data.is_empty() {
+                None
+            } else {
+                Some(string_data)
+            },
+            ..Default::default()
+        };
+
+        Ok(Some(secret))
+    }
+}
+
+/// Convert `data` into raw bytes. ByteString serialises back to base64, so
+/// already-encoded input is decoded first to validate it. Whitespace is
+/// dropped beforehand, as `base64` wraps its output every 76 characters, and
+/// the canonical encoding that comes back out is the input without it.
+fn secret_data(
+    name: &str,
+    secret_config: &SecretConfig,
+) -> Result<BTreeMap<String, ByteString>, BunkuError> {
+    secret_config
+        .data
+        .iter()
+        .map(|(key, value)| {
+            let bytes = if secret_config.encoded {
+                let compact: String = value.split_ascii_whitespace().collect();
+                STANDARD.decode(compact).map_err(|e| {
+                    BunkuError::invalid(
+                        format!("secrets.{}.data.{}", name, key),
+                        format!("invalid base64: {}", e),
+                    )
+                })?
+            } else {
+                value.as_bytes().to_vec()
+            };
+            Ok((key.clone(), ByteString(bytes)))
+        })
+        .collect()
+}
+
+/// Check every enabled secret for valid data and the keys its well-known
+/// type requires.
+pub fn validate(values: &Values) -> Result<(), BunkuError> {
+    let mut errors = Errors::default();
+    for (name, secret_config) in &values.secrets {
+        if !secret_config.enabled {
+            continue;
+        }
+
+        let data_valid = errors.capture(secret_data(name, secret_config)).is_some();
+
+        let has_key = |key: &str| {
+            secret_config.data.contains_key(key) || secret_config.string_data.contains_key(key)
+        };
+        let require = |keys: &[&str]| -> Result<(), BunkuError> {
+            match keys.iter().find(|key| !has_key(key)) {
+                Some(missing) => Err(BunkuError::invalid(
+                    format!("secrets.{}", name),
+                    format!("type {} requires key {:?}", secret_config.r#type, missing),
+                )),
+                None => Ok(()),
+            }
+        };
+
+        match secret_config.r#type.as_str() {
+            "kubernetes.io/tls" => errors.capture(require(&["tls.crt", "tls.key"])),
+            "kubernetes.io/ssh-auth" => errors.capture(require(&["ssh-privatekey"])),
+            "kubernetes.io/dockercfg" => errors.capture(require(&[".dockercfg"])),
+            "kubernetes.io/dockerconfigjson" => errors
+                .capture(require(&[".dockerconfigjson"]))
+                .filter(|()| data_valid)
+                .and_then(|()| errors.capture(validate_docker_config_json(name, secret_config))),
+            "kubernetes.io/basic-auth" if !has_key("username") && !has_key("password") => errors
+                .capture(Err(BunkuError::invalid(
+                    format!("secrets.{}", name),
+                    "type kubernetes.io/basic-auth requires key \"username\" or \"password\"",
+                ))),
+            _ => None,
+        };
+    }
+
+    errors.finish()
+}
+
+fn validate_docker_config_json(name: &str, secret_config: &SecretConfig) -> Result<(), BunkuError> {
+    let key = ".dockerconfigjson";
+    let content = match secret_config.string_data.get(key) {
+        Some(value) => value.as_bytes().to_vec(),
+        None => secret_data(name, secret_config)?
+            .remove(key)
+            .map(|bytes| bytes.0)
+            .unwrap_or_default(),
+    };
+
+    serde_json::from_slice::<serde_json::Value>(&content)
+        .map(|_| ())
+        .map_err(|e| {
+            BunkuError::invalid(
+                format!("secrets.{}", name),
+                format!("{} is not valid JSON: {}", key, e),
+            )
+        })
+}
@@ -1,4 +1,5 @@
 use super::{ResourceGenerator, common};
+use crate::error::BunkuError;
 use crate::values::Values;
 use k8s_openapi::api::core::v1::{Service, ServicePort, ServiceSpec};
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
@@ -8,11 +9,13 @@ pub struct ServiceGenerator;
 impl ResourceGenerator for ServiceGenerator {
     type Output = Service;
 
-    fn generate(&self, values: &Values, name: &str) -> Option<Self::Output> {
-        let service_config = values.service.get(name)?;
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(service_config) = values.service.get(name) else {
+            return Ok(None);
+        };
 
         if !service_config.enabled {
-            return None;
+            return Ok(None);
         }
 
         let service_name = common::generate_name(values, name);
@@ -48,7 +51,7 @@ impl ResourceGenerator for ServiceGenerator {
             .collect();
 
         if ports.is_empty() {
-            return None;
+            return Ok(None);
         }
 
         let mut service_labels = common::generate_labels(values, name, "service");
@@ -91,10 +94,11 @@ impl ResourceGenerator for ServiceGenerator {
                 external_traffic_policy: service_config.external_traffic_policy.clone(),
                 allocate_load_balancer_node_ports: service_config.allocate_load_balancer_node_ports,
                 session_affinity: service_config.session_affinity.clone(),
-                session_affinity_config: service_config
-                    .session_affinity_config
-                    .as_ref()
-                    .and_then(|sac| serde_json::from_value(sac.clone()).ok()),
+                session_affinity_config: common::convert_optional(
+                    &format!("service.{}", name),
+                    "sessionAffinityConfig",
+                    &service_config.session_affinity_config,
+                )?,
                 external_ips: service_config.external_ips.clone(),
                 publish_not_ready_addresses: service_config.publish_not_ready_addresses,
                 ip_family_policy: service_config.ip_family_policy.clone(),
@@ -104,6 +108,6 @@ impl ResourceGenerator for ServiceGenerator {
             ..Default::default()
         };
 
-        Some(service)
+        Ok(Some(service))
     }
 }
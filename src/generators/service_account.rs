@@ -1,4 +1,5 @@
 use super::{ResourceGenerator, common};
+use crate::error::BunkuError;
 use crate::values::Values;
 use k8s_openapi::api::core::v1::{ObjectReference, ServiceAccount};
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
@@ -8,11 +9,13 @@ pub struct ServiceAccountGenerator;
 impl ResourceGenerator for ServiceAccountGenerator {
     type Output = ServiceAccount;
 
-    fn generate(&self, values: &Values, name: &str) -> Option<Self::Output> {
-        let sa_config = values.service_account.get(name)?;
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(sa_config) = values.service_account.get(name) else {
+            return Ok(None);
+        };
 
         if !sa_config.enabled {
-            return None;
+            return Ok(None);
         }
 
         let sa_name = common::generate_name(values, name);
@@ -28,6 +31,7 @@ impl ResourceGenerator for ServiceAccountGenerator {
                 .map(|(k, v)| (k.clone(), v.clone())),
         );
 
+        // Bunku-managed Secrets by identifier, or any other name verbatim
         let secrets = if sa_config.secrets.is_empty() {
             None
         } else {
@@ -35,8 +39,12 @@ impl ResourceGenerator for ServiceAccountGenerator {
                 sa_config
                     .secrets
                     .iter()
-                    .map(|secret_name| ObjectReference {
-                        name: Some(secret_name.clone()),
+                    .map(|secret| ObjectReference {
+                        name: Some(if values.secrets.contains_key(secret) {
+                            common::generate_name(values, secret)
+                        } else {
+                            secret.clone()
+                        }),
                         ..Default::default()
                     })
                     .collect(),
@@ -59,6 +67,6 @@ impl ResourceGenerator for ServiceAccountGenerator {
             ..Default::default()
         };
 
-        Some(service_account)
+        Ok(Some(service_account))
     }
 }
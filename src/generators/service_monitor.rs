@@ -0,0 +1,128 @@
+use super::{ResourceGenerator, common};
+use crate::error::BunkuError;
+use crate::references;
+use crate::values::{ServiceMonitor as ServiceMonitorConfig, Values};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+/// Labels from `generate_labels` that identify a bunku Service.
+const SELECTOR_LABELS: [&str; 2] = ["app.kubernetes.io/instance", "app.kubernetes.io/service"];
+
+pub struct ServiceMonitorGenerator;
+
+impl ResourceGenerator for ServiceMonitorGenerator {
+    // Prometheus Operator types are not in k8s-openapi, so emit plain JSON
+    type Output = Value;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(monitor_config) = values.service_monitor.get(name) else {
+            return Ok(None);
+        };
+
+        if !monitor_config.enabled {
+            return Ok(None);
+        }
+
+        // Without its Service the monitor selects nothing, so this stays an
+        // error even when references are only warnings
+        let service_key = service_key(name, monitor_config);
+        let service_config = values.service.get(service_key).ok_or_else(|| {
+            references::undefined(
+                format!("serviceMonitor.{}.service", name),
+                "service",
+                service_key,
+            )
+        })?;
+
+        let mut labels = common::generate_labels(values, name, "servicemonitor");
+        labels.extend(
+            monitor_config
+                .labels
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        let mut annotations = common::generate_annotations(values);
+        annotations.extend(
+            monitor_config
+                .annotations
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        let match_labels: BTreeMap<String, String> =
+            common::generate_labels(values, service_key, "service")
+                .into_iter()
+                .filter(|(k, _)| SELECTOR_LABELS.contains(&k.as_str()))
+                .collect();
+
+        // Without endpoints, scrape the Service's first port
+        let endpoints: Vec<Value> = if monitor_config.endpoints.is_empty() {
+            let port = service_config.ports.keys().next().ok_or_else(|| {
+                BunkuError::invalid(
+                    format!("serviceMonitor.{}", name),
+                    format!("service {:?} has no ports to scrape", service_key),
+                )
+            })?;
+            vec![endpoint(port, None, None, None, monitor_config)]
+        } else {
+            monitor_config
+                .endpoints
+                .iter()
+                .map(|e| {
+                    endpoint(
+                        &e.port,
+                        e.path.as_deref(),
+                        e.interval.as_deref(),
+                        e.scrape_timeout.as_deref(),
+                        monitor_config,
+                    )
+                })
+                .collect()
+        };
+
+        let mut metadata = json!({
+            "name": common::generate_name(values, name),
+            "labels": labels,
+        });
+        if !annotations.is_empty() {
+            metadata["annotations"] = json!(annotations);
+        }
+
+        Ok(Some(json!({
+            "apiVersion": "monitoring.coreos.com/v1",
+            "kind": "ServiceMonitor",
+            "metadata": metadata,
+            "spec": {
+                "selector": { "matchLabels": match_labels },
+                "endpoints": endpoints,
+            },
+        })))
+    }
+}
+
+fn service_key<'a>(name: &'a str, monitor_config: &'a ServiceMonitorConfig) -> &'a str {
+    monitor_config.service.as_deref().unwrap_or(name)
+}
+
+/// Build an endpoint, falling back to the monitor-wide path, interval and
+/// scrape timeout.
+fn endpoint(
+    port: &str,
+    path: Option<&str>,
+    interval: Option<&str>,
+    scrape_timeout: Option<&str>,
+    monitor_config: &ServiceMonitorConfig,
+) -> Value {
+    let mut endpoint = json!({
+        "port": port,
+        "interval": interval.unwrap_or(&monitor_config.interval),
+    });
+    if let Some(path) = path.or(monitor_config.path.as_deref()) {
+        endpoint["path"] = json!(path);
+    }
+    if let Some(scrape_timeout) = scrape_timeout.or(monitor_config.scrape_timeout.as_deref()) {
+        endpoint["scrapeTimeout"] = json!(scrape_timeout);
+    }
+    endpoint
+}
@@ -0,0 +1,198 @@
+use super::{ResourceGenerator, common, pod, pvc};
+use crate::error::{BunkuError, Errors};
+use crate::values::{Controller, Values};
+use k8s_openapi::api::apps::v1::{
+    RollingUpdateStatefulSetStrategy, StatefulSet, StatefulSetSpec, StatefulSetUpdateStrategy,
+};
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, Service, ServicePort, ServiceSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta};
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+
+pub struct StatefulSetGenerator;
+
+impl ResourceGenerator for StatefulSetGenerator {
+    type Output = StatefulSet;
+
+    fn generate(&self, values: &Values, name: &str) -> Result<Option<Self::Output>, BunkuError> {
+        let Some(controller) = values.controllers.get(name) else {
+            return Ok(None);
+        };
+
+        if !controller.enabled || controller.r#type != "statefulset" {
+            return Ok(None);
+        }
+
+        let selector_labels = common::generate_selector_labels(values, name);
+        let Some(template) = pod::generate_pod_template(values, name, controller)? else {
+            return Ok(None);
+        };
+        let statefulset_config = controller.statefulset.as_ref();
+
+        let mut errors = Errors::default();
+        let volume_claim_templates: Vec<PersistentVolumeClaim> = statefulset_config
+            .map(|config| {
+                config
+                    .volume_claim_templates
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, item)| item.enabled)
+                    .filter_map(|(index, item)| {
+                        let path = format!(
+                            "controllers.{}.statefulset.volumeClaimTemplates[{}]",
+                            name, index
+                        );
+                        let Some(claim_name) = item.name.clone() else {
+                            errors.push(BunkuError::invalid(path, "missing name"));
+                            return None;
+                        };
+
+                        let mut labels = selector_labels.clone();
+                        labels.extend(item.labels.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+                        Some(PersistentVolumeClaim {
+                            metadata: ObjectMeta {
+                                name: Some(claim_name),
+                                labels: Some(labels),
+                                annotations: if item.annotations.is_empty() {
+                                    None
+                                } else {
+                                    Some(
+                                        item.annotations
+                                            .iter()
+                                            .map(|(k, v)| (k.clone(), v.clone()))
+                                            .collect(),
+                                    )
+                                },
+                                ..Default::default()
+                            },
+                            spec: Some(errors.capture(pvc::generate_pvc_spec(item, &path))?),
+                            ..Default::default()
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        errors.finish()?;
+
+        let update_strategy =
+            if controller.strategy.is_some() || controller.rolling_update.is_some() {
+                Some(StatefulSetUpdateStrategy {
+                    type_: Some(
+                        controller
+                            .strategy
+                            .clone()
+                            .unwrap_or_else(|| "RollingUpdate".to_string()),
+                    ),
+                    rolling_update: controller.rolling_update.as_ref().map(|ru| {
+                        RollingUpdateStatefulSetStrategy {
+                            partition: ru.partition,
+                            max_unavailable: ru.unavailable.as_deref().map(common::int_or_string),
+                        }
+                    }),
+                })
+            } else {
+                None
+            };
+
+        let statefulset = StatefulSet {
+            metadata: common::generate_controller_metadata(values, name, controller),
+            spec: Some(StatefulSetSpec {
+                replicas: controller.replicas,
+                revision_history_limit: Some(controller.revision_history_limit),
+                selector: LabelSelector {
+                    match_labels: Some(selector_labels),
+                    ..Default::default()
+                },
+                service_name: governing_service_name(values, name, controller),
+                pod_management_policy: statefulset_config
+                    .and_then(|config| config.pod_management_policy.clone()),
+                update_strategy,
+                template,
+                volume_claim_templates: if volume_claim_templates.is_empty() {
+                    None
+                } else {
+                    Some(volume_claim_templates)
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        Ok(Some(statefulset))
+    }
+}
+
+/// Generate the headless governing Service for a StatefulSet that does not
+/// reference one through `statefulset.serviceName`.
+pub fn generate_headless_service(values: &Values, name: &str) -> Option<Service> {
+    let controller = values.controllers.get(name)?;
+
+    if !controller.enabled || controller.r#type != "statefulset" {
+        return None;
+    }
+    if controller
+        .statefulset
+        .as_ref()
+        .is_some_and(|config| config.service_name.is_some())
+    {
+        return None;
+    }
+
+    let service_key = headless_service_key(name);
+
+    let ports: Vec<ServicePort> = controller
+        .containers
+        .values()
+        .flat_map(|container| container.ports.iter())
+        .map(|(port_name, port_config)| ServicePort {
+            name: Some(port_name.clone()),
+            port: port_config.container_port,
+            target_port: Some(IntOrString::String(port_name.clone())),
+            protocol: port_config.protocol.clone(),
+            ..Default::default()
+        })
+        .collect();
+
+    let annotations = common::generate_annotations(values);
+
+    Some(Service {
+        metadata: ObjectMeta {
+            name: Some(common::generate_name(values, &service_key)),
+            labels: Some(common::generate_labels(values, &service_key, "service")),
+            annotations: if annotations.is_empty() {
+                None
+            } else {
+                Some(annotations)
+            },
+            ..Default::default()
+        },
+        spec: Some(ServiceSpec {
+            type_: Some("ClusterIP".to_string()),
+            cluster_ip: Some("None".to_string()),
+            selector: Some(common::generate_selector_labels(values, name)),
+            ports: if ports.is_empty() { None } else { Some(ports) },
+            publish_not_ready_addresses: Some(true),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+fn headless_service_key(name: &str) -> String {
+    format!("{}-headless", name)
+}
+
+fn governing_service_name(values: &Values, name: &str, controller: &Controller) -> String {
+    match controller
+        .statefulset
+        .as_ref()
+        .and_then(|config| config.service_name.as_ref())
+    {
+        // Resolve bunku Service identifiers, pass anything else through verbatim
+        Some(service_name) if values.service.contains_key(service_name) => {
+            common::generate_name(values, service_name)
+        }
+        Some(service_name) => service_name.clone(),
+        None => common::generate_name(values, &headless_service_key(name)),
+    }
+}
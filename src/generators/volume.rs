@@ -0,0 +1,280 @@
+use super::{common, pvc};
+use crate::error::{BunkuError, Errors};
+use crate::references;
+use crate::values::{Controller, GlobalMount, PersistenceItem, Values};
+use indexmap::IndexMap;
+use k8s_openapi::api::core::v1::{
+    ConfigMapVolumeSource, Container, EmptyDirVolumeSource, EphemeralVolumeSource,
+    HostPathVolumeSource, ImageVolumeSource, NFSVolumeSource, PersistentVolumeClaimTemplate,
+    PersistentVolumeClaimVolumeSource, SecretVolumeSource, Volume, VolumeMount as K8sVolumeMount,
+};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+/// Persistence types bunku knows how to render.
+const TYPES: [&str; 8] = [
+    "pvc",
+    "configMap",
+    "secret",
+    "emptyDir",
+    "hostPath",
+    "nfs",
+    "image",
+    "ephemeral",
+];
+
+/// Check the type of every enabled persistence entry, including those no
+/// container mounts yet.
+pub fn validate(values: &Values) -> Result<(), BunkuError> {
+    let mut errors = Errors::default();
+    for (key, item) in &values.persistence {
+        if item.enabled {
+            errors.capture(check_type(key, item));
+        }
+    }
+    errors.finish()
+}
+
+fn check_type(key: &str, item: &PersistenceItem) -> Result<(), BunkuError> {
+    if TYPES.contains(&item.r#type.as_str()) {
+        return Ok(());
+    }
+    Err(BunkuError::invalid(
+        format!("persistence.{}.type", key),
+        format!(
+            "unknown type {:?}, expected one of {}",
+            item.r#type,
+            TYPES.join(", ")
+        ),
+    ))
+}
+
+/// Build the pod volumes for every enabled persistence entry the controller
+/// uses, either through a container `volumeMounts` entry or a `globalMounts`
+/// entry that applies to one of its containers.
+pub fn generate_volumes(
+    values: &Values,
+    controller_name: &str,
+    controller: &Controller,
+) -> Result<Vec<Volume>, BunkuError> {
+    let mut errors = Errors::default();
+    let volumes = values
+        .persistence
+        .iter()
+        .filter(|(_, item)| item.enabled)
+        .filter(|(key, item)| {
+            controller
+                .containers
+                .iter()
+                .chain(controller.init_containers.iter())
+                .any(|(container_name, container)| {
+                    container.volume_mounts.iter().any(|vm| &vm.name == *key)
+                        || item
+                            .global_mounts
+                            .iter()
+                            .any(|mount| mount_applies(mount, controller_name, container_name))
+                })
+        })
+        .filter_map(|(key, item)| errors.capture(generate_volume(values, key, item)))
+        .collect();
+    errors.finish()?;
+    Ok(volumes)
+}
+
+/// Check that every container mount names one of the pod's volumes or, in a
+/// StatefulSet, one of its claim templates. A mount without its volume makes
+/// the pod invalid, so this is an error even when references are only
+/// warnings.
+pub fn check_mounts(
+    values: &Values,
+    controller_name: &str,
+    controller: &Controller,
+    volumes: &[Volume],
+) -> Result<(), BunkuError> {
+    let claim_templates: Vec<&str> = match &controller.statefulset {
+        Some(statefulset) if controller.r#type == "statefulset" => statefulset
+            .volume_claim_templates
+            .iter()
+            .filter(|item| item.enabled)
+            .filter_map(|item| item.name.as_deref())
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut errors = Errors::default();
+    for (field, containers) in [
+        ("containers", &controller.containers),
+        ("initContainers", &controller.init_containers),
+    ] {
+        for (container_name, container) in containers {
+            for (index, mount) in container.volume_mounts.iter().enumerate() {
+                if volumes.iter().any(|volume| volume.name == mount.name)
+                    || claim_templates.contains(&mount.name.as_str())
+                {
+                    continue;
+                }
+                let path = format!(
+                    "controllers.{}.{}.{}.volumeMounts[{}].name",
+                    controller_name, field, container_name, index
+                );
+                errors.push(if values.persistence.contains_key(&mount.name) {
+                    references::disabled(path, "persistence", &mount.name)
+                } else {
+                    references::undefined(path, "persistence", &mount.name)
+                });
+            }
+        }
+    }
+    errors.finish()
+}
+
+/// Append the `globalMounts` of every enabled persistence entry that targets
+/// this container.
+pub fn add_global_mounts(values: &Values, controller_name: &str, container: &mut Container) {
+    let mounts: Vec<K8sVolumeMount> = values
+        .persistence
+        .iter()
+        .filter(|(_, item)| item.enabled)
+        .flat_map(|(key, item)| {
+            item.global_mounts
+                .iter()
+                .filter(|mount| mount_applies(mount, controller_name, &container.name))
+                .map(move |mount| K8sVolumeMount {
+                    name: key.clone(),
+                    mount_path: mount.mount_path.clone(),
+                    read_only: mount.read_only,
+                    sub_path: mount.sub_path.clone(),
+                    ..Default::default()
+                })
+        })
+        .collect();
+
+    if !mounts.is_empty() {
+        container
+            .volume_mounts
+            .get_or_insert_with(Vec::new)
+            .extend(mounts);
+    }
+}
+
+fn mount_applies(mount: &GlobalMount, controller_name: &str, container_name: &str) -> bool {
+    (mount.controllers.is_empty() || mount.controllers.iter().any(|c| c == controller_name))
+        && (mount.containers.is_empty() || mount.containers.iter().any(|c| c == container_name))
+}
+
+fn generate_volume(
+    values: &Values,
+    key: &str,
+    item: &PersistenceItem,
+) -> Result<Volume, BunkuError> {
+    let path = format!("persistence.{}", key);
+    let required = |field: &str, value: &Option<String>| {
+        value.clone().ok_or_else(|| {
+            BunkuError::invalid(
+                format!("{}.{}", path, field),
+                format!("required for type {:?}", item.r#type),
+            )
+        })
+    };
+
+    let mut volume = Volume {
+        name: key.to_string(),
+        ..Default::default()
+    };
+
+    match item.r#type.as_str() {
+        "pvc" => {
+            volume.persistent_volume_claim = Some(PersistentVolumeClaimVolumeSource {
+                claim_name: common::generate_name(values, key),
+                ..Default::default()
+            });
+        }
+        "configMap" => {
+            volume.config_map = Some(ConfigMapVolumeSource {
+                name: referenced_name(values, &values.config_maps, key, item),
+                default_mode: item.default_mode,
+                ..Default::default()
+            });
+        }
+        "secret" => {
+            volume.secret = Some(SecretVolumeSource {
+                secret_name: Some(referenced_name(values, &values.secrets, key, item)),
+                default_mode: item.default_mode,
+                ..Default::default()
+            });
+        }
+        "emptyDir" => {
+            volume.empty_dir = Some(EmptyDirVolumeSource {
+                medium: item.medium.clone(),
+                size_limit: item.size_limit.clone().map(Quantity),
+            });
+        }
+        "hostPath" => {
+            volume.host_path = Some(HostPathVolumeSource {
+                path: required("hostPath", &item.host_path)?,
+                type_: item.host_path_type.clone(),
+            });
+        }
+        "nfs" => {
+            volume.nfs = Some(NFSVolumeSource {
+                server: required("server", &item.server)?,
+                path: required("path", &item.path)?,
+                ..Default::default()
+            });
+        }
+        "image" => {
+            volume.image = Some(ImageVolumeSource {
+                reference: Some(required("image", &item.image)?),
+                pull_policy: item.pull_policy.clone(),
+            });
+        }
+        "ephemeral" => {
+            let mut labels = common::generate_labels(values, key, "pvc");
+            labels.extend(item.labels.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+            volume.ephemeral = Some(EphemeralVolumeSource {
+                volume_claim_template: Some(PersistentVolumeClaimTemplate {
+                    metadata: Some(ObjectMeta {
+                        labels: Some(labels),
+                        annotations: if item.annotations.is_empty() {
+                            None
+                        } else {
+                            Some(
+                                item.annotations
+                                    .iter()
+                                    .map(|(k, v)| (k.clone(), v.clone()))
+                                    .collect(),
+                            )
+                        },
+                        ..Default::default()
+                    }),
+                    spec: pvc::generate_pvc_spec(item, &path)?,
+                }),
+            });
+        }
+        _ => check_type(key, item)?,
+    }
+
+    Ok(volume)
+}
+
+/// Resolve the object a configMap/secret volume points at: a bunku identifier,
+/// a literal name, or the bunku entry sharing the persistence key.
+fn referenced_name<T>(
+    values: &Values,
+    entries: &IndexMap<String, T>,
+    key: &str,
+    item: &PersistenceItem,
+) -> String {
+    if let Some(identifier) = &item.identifier {
+        return common::generate_name(values, identifier);
+    }
+    if let Some(name) = &item.name {
+        return name.clone();
+    }
+    if entries.contains_key(key) {
+        common::generate_name(values, key)
+    } else {
+        key.to_string()
+    }
+}
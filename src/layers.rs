@@ -0,0 +1,139 @@
+use crate::error::BunkuError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// Top-level key listing files this one builds on, relative to itself.
+pub const EXTENDS_KEY: &str = "extends";
+
+/// Marker that deletes an inherited table entry, e.g.
+/// `[controllers.worker]` with `"$delete" = true`.
+pub const DELETE_KEY: &str = "$delete";
+
+/// A values file as read from disk, kept to locate keys in error messages.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: String,
+    pub content: String,
+}
+
+/// The merged values table and every file that contributed to it, in merge
+/// order.
+#[derive(Debug, Clone, Default)]
+pub struct LayeredValues {
+    pub table: Table,
+    pub sources: Vec<SourceFile>,
+}
+
+/// Load and merge values files in order, later files overriding earlier ones.
+pub fn load_values_files<P: AsRef<Path>>(filenames: &[P]) -> Result<LayeredValues, BunkuError> {
+    let mut layered = LayeredValues::default();
+    for filename in filenames {
+        // Each file layers onto the ones before it, as if it extended them,
+        // so its `$delete` markers reach entries from earlier files
+        let base = std::mem::take(&mut layered.table);
+        layered.table = load_file(
+            filename.as_ref(),
+            base,
+            &mut Vec::new(),
+            &mut layered.sources,
+        )?;
+    }
+    Ok(layered)
+}
+
+/// Layer one file onto `base`, with everything it extends merged in between.
+fn load_file(
+    path: &Path,
+    mut base: Table,
+    stack: &mut Vec<PathBuf>,
+    sources: &mut Vec<SourceFile>,
+) -> Result<Table, BunkuError> {
+    let in_file = |source: BunkuError| BunkuError::File {
+        path: path.display().to_string(),
+        source: Box::new(source),
+    };
+
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| in_file(BunkuError::Io(e)))?;
+    if stack.contains(&canonical) {
+        return Err(in_file(BunkuError::Validation(
+            "extends cycle detected".to_string(),
+        )));
+    }
+
+    let content = fs::read_to_string(path).map_err(|e| in_file(BunkuError::Io(e)))?;
+    let mut table: Table = toml::from_str(&content).map_err(|e| in_file(e.into()))?;
+
+    let extends = match table.remove(EXTENDS_KEY) {
+        None => Vec::new(),
+        Some(Value::String(parent)) => vec![parent],
+        Some(Value::Array(parents)) => parents
+            .into_iter()
+            .map(|parent| match parent {
+                Value::String(parent) => Ok(parent),
+                other => Err(in_file(BunkuError::Validation(format!(
+                    "{} entries must be strings, found {}",
+                    EXTENDS_KEY,
+                    other.type_str()
+                )))),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(other) => {
+            return Err(in_file(BunkuError::Validation(format!(
+                "{} must be a string or an array of strings, found {}",
+                EXTENDS_KEY,
+                other.type_str()
+            ))));
+        }
+    };
+
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    stack.push(canonical);
+    for parent in extends {
+        base = load_file(&base_dir.join(parent), base, stack, sources)?;
+    }
+    stack.pop();
+
+    sources.push(SourceFile {
+        path: path.display().to_string(),
+        content,
+    });
+    merge_tables(&mut base, table);
+    Ok(base)
+}
+
+/// Deep-merge `overlay` into `base`. Tables merge by key, everything else is
+/// replaced. A table with `"$delete" = true` removes the inherited entry; any
+/// other keys next to the marker replace it instead of merging into it.
+pub fn merge_tables(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match value {
+            Value::Table(mut table) if is_delete_marker(&table) => {
+                table.remove(DELETE_KEY);
+                base.remove(&key);
+                if !table.is_empty() {
+                    let mut fresh = Table::new();
+                    merge_tables(&mut fresh, table);
+                    base.insert(key, Value::Table(fresh));
+                }
+            }
+            Value::Table(table) => match base.get_mut(&key) {
+                Some(Value::Table(existing)) => merge_tables(existing, table),
+                _ => {
+                    let mut fresh = Table::new();
+                    merge_tables(&mut fresh, table);
+                    base.insert(key, Value::Table(fresh));
+                }
+            },
+            value => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn is_delete_marker(table: &Table) -> bool {
+    matches!(table.get(DELETE_KEY), Some(Value::Boolean(true)))
+}
@@ -1,23 +1,151 @@
+pub mod diagnostics;
 pub mod error;
 pub mod generators;
+pub mod layers;
+pub mod output;
+pub mod overrides;
+pub mod references;
+pub mod schema;
 pub mod values;
 
 pub use generators::generate_all_resources;
 pub use values::Values;
 
 use serde_json::Value;
-use std::fs;
+
+/// Settings from the command line that override the values file.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Release name, overriding `global.releaseName`.
+    pub name: Option<String>,
+    /// Namespace, overriding `global.namespace`.
+    pub namespace: Option<String>,
+    /// `key.path=value` overrides, values parsed as TOML where possible.
+    pub set: Vec<String>,
+    /// `key.path=value` overrides, values always strings.
+    pub set_string: Vec<String>,
+    /// `key.path=file` overrides, values read from the file.
+    pub set_file: Vec<String>,
+    /// Ignore keys bunku does not know instead of rejecting them.
+    pub allow_unknown_keys: bool,
+    /// Report dangling references as warnings instead of errors.
+    pub reference_warnings: bool,
+}
+
+/// Rendered resources and the problems that were downgraded to warnings.
+#[derive(Debug, Default)]
+pub struct Rendered {
+    pub resources: Vec<Value>,
+    pub warnings: Vec<error::BunkuError>,
+}
 
 pub fn process_values_file(filename: &str) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
-    let content = fs::read_to_string(filename)?;
-    let values: Values = toml::from_str(&content)?;
-    let resources = generate_all_resources(&values);
+    render_values_file(filename, &RenderOptions::default())
+}
+
+pub fn render_values_file(
+    filename: &str,
+    options: &RenderOptions,
+) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
+    render_values_files(&[filename], options)
+}
+
+/// Render several values files, deep-merged in order with their `extends`.
+/// Warnings are dropped, use [`render`] to see them.
+pub fn render_values_files<P: AsRef<std::path::Path>>(
+    filenames: &[P],
+    options: &RenderOptions,
+) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
+    Ok(render(filenames, options)?.resources)
+}
+
+/// Render several values files, returning the resources with any warnings.
+pub fn render<P: AsRef<std::path::Path>>(
+    filenames: &[P],
+    options: &RenderOptions,
+) -> Result<Rendered, Box<dyn std::error::Error>> {
+    let mut warnings = Vec::new();
+    let resources = render_resources(filenames, options, &mut Vec::new(), &mut warnings)?;
+    Ok(Rendered {
+        resources,
+        warnings,
+    })
+}
+
+/// Run every check `render` does and report each problem found, errors and
+/// warnings alike, without returning any resources. The values are valid
+/// when no diagnostic is an error.
+pub fn validate<P: AsRef<std::path::Path>>(
+    filenames: &[P],
+    options: &RenderOptions,
+) -> Vec<diagnostics::Diagnostic> {
+    use diagnostics::{Severity, diagnose};
+
+    let mut sources = Vec::new();
+    let mut warnings = Vec::new();
+    let result = render_resources(filenames, options, &mut sources, &mut warnings);
+
+    let mut report: Vec<_> = warnings
+        .into_iter()
+        .flat_map(|warning| diagnose(warning, Severity::Warning, &sources))
+        .collect();
+    if let Err(error) = result {
+        report.extend(diagnose(error, Severity::Error, &sources));
+    }
+    report
+}
+
+/// The render pipeline, keeping the files read and the warnings raised so
+/// callers can report them even when rendering fails.
+fn render_resources<P: AsRef<std::path::Path>>(
+    filenames: &[P],
+    options: &RenderOptions,
+    sources: &mut Vec<layers::SourceFile>,
+    warnings: &mut Vec<error::BunkuError>,
+) -> Result<Vec<Value>, error::BunkuError> {
+    let mut layered = layers::load_values_files(filenames)?;
+    *sources = std::mem::take(&mut layered.sources);
+    overrides::apply_overrides(
+        &mut layered.table,
+        &options.set,
+        &options.set_string,
+        &options.set_file,
+    )?;
+    let mut values =
+        schema::deserialize_values(layered.table, sources, !options.allow_unknown_keys)?;
+    if let Some(name) = &options.name {
+        values.global.release_name = Some(name.clone());
+    }
+    if let Some(namespace) = &options.namespace {
+        values.global.namespace = Some(namespace.clone());
+    }
+
+    let problems = references::check(&values);
+    let mut generation = error::Errors::default();
+    let resources = generation.capture(generate_all_resources(&values));
+
+    // Generators report the references they cannot render, like an ingress
+    // path without its Service, so those stay errors in either mode
+    let mut errors = error::Errors::default();
+    for problem in problems {
+        if options.reference_warnings && !generation.contains(&problem) {
+            warnings.push(problem);
+        } else {
+            errors.push(problem);
+        }
+    }
+    errors.capture(generation.finish());
+    errors.finish()?;
+
+    let resources = resources.expect("generation succeeds when no errors are found");
+    generators::raw_resource::validate_unique(&values, &resources)?;
     Ok(resources)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::BunkuError;
     use std::io::Write;
     use tempfile::NamedTempFile;
 
@@ -40,7 +168,7 @@ protocol = "TCP"
 "#;
 
         let values: Values = toml::from_str(toml_content).unwrap();
-        let resources = generate_all_resources(&values);
+        let resources = generate_all_resources(&values).unwrap();
 
         assert!(!resources.is_empty());
         // Should generate a deployment
@@ -74,7 +202,7 @@ targetPort = 80
 "#;
 
         let values: Values = toml::from_str(toml_content).unwrap();
-        let resources = generate_all_resources(&values);
+        let resources = generate_all_resources(&values).unwrap();
 
         // Should generate a deployment and a service
         assert_eq!(resources.len(), 2);
@@ -114,7 +242,7 @@ key2 = "value2"
 "#;
 
         let values: Values = toml::from_str(toml_content).unwrap();
-        let resources = generate_all_resources(&values);
+        let resources = generate_all_resources(&values).unwrap();
 
         assert_eq!(resources.len(), 1);
     }
@@ -132,7 +260,7 @@ accessModes = ["ReadWriteOnce"]
 "#;
 
         let values: Values = toml::from_str(toml_content).unwrap();
-        let resources = generate_all_resources(&values);
+        let resources = generate_all_resources(&values).unwrap();
 
         assert_eq!(resources.len(), 1);
     }
@@ -161,7 +289,7 @@ targetPort = 80
 "#;
 
         let values: Values = toml::from_str(toml_content).unwrap();
-        let resources = generate_all_resources(&values);
+        let resources = generate_all_resources(&values).unwrap();
 
         // Create List object
         let list = serde_json::json!({
@@ -176,4 +304,1914 @@ targetPort = 80
         assert!(list["items"].is_array());
         assert_eq!(list["items"].as_array().unwrap().len(), 2);
     }
+
+    #[test]
+    fn test_statefulset_generation() {
+        let toml_content = r#"
+[controllers.db]
+type = "statefulset"
+replicas = 3
+
+[controllers.db.rollingUpdate]
+partition = 1
+
+[controllers.db.statefulset]
+podManagementPolicy = "Parallel"
+
+[[controllers.db.statefulset.volumeClaimTemplates]]
+name = "data"
+size = "20Gi"
+storageClass = "fast-ssd"
+
+[controllers.db.containers.postgres]
+image = "postgres:16"
+
+[controllers.db.containers.postgres.ports.postgres]
+containerPort = 5432
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+
+        assert_eq!(resources.len(), 2);
+
+        let service = &resources[0];
+        assert_eq!(service["kind"], "Service");
+        assert_eq!(service["metadata"]["name"], "db-headless");
+        assert_eq!(service["spec"]["clusterIP"], "None");
+        assert_eq!(service["spec"]["ports"][0]["port"], 5432);
+
+        let statefulset = &resources[1];
+        assert_eq!(statefulset["kind"], "StatefulSet");
+        assert_eq!(statefulset["spec"]["serviceName"], "db-headless");
+        assert_eq!(statefulset["spec"]["podManagementPolicy"], "Parallel");
+        assert_eq!(
+            statefulset["spec"]["updateStrategy"]["rollingUpdate"]["partition"],
+            1
+        );
+        let claim = &statefulset["spec"]["volumeClaimTemplates"][0];
+        assert_eq!(claim["metadata"]["name"], "data");
+        assert_eq!(claim["spec"]["resources"]["requests"]["storage"], "20Gi");
+        assert_eq!(claim["spec"]["storageClassName"], "fast-ssd");
+
+        // Nothing to run means no StatefulSet, and no governing Service either
+        let empty: Values = toml::from_str(
+            r#"
+[controllers.db]
+type = "statefulset"
+"#,
+        )
+        .unwrap();
+        assert!(generate_all_resources(&empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_statefulset_references_existing_service() {
+        let toml_content = r#"
+[controllers.db]
+type = "statefulset"
+
+[controllers.db.statefulset]
+serviceName = "db"
+
+[controllers.db.containers.postgres]
+image = "postgres:16"
+
+[service.db]
+controller = "db"
+clusterIP = "None"
+
+[service.db.ports.postgres]
+port = 5432
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+
+        // No headless Service is generated when one is referenced
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0]["kind"], "Service");
+        assert_eq!(resources[1]["kind"], "StatefulSet");
+        assert_eq!(resources[1]["spec"]["serviceName"], "db");
+    }
+
+    #[test]
+    fn test_cronjob_generation() {
+        let toml_content = r#"
+[controllers.report]
+type = "cronjob"
+
+[controllers.report.cronjob]
+schedule = "0 2 * * *"
+timeZone = "Etc/UTC"
+successfulJobsHistory = 3
+ttlSecondsAfterFinished = 600
+
+[controllers.report.containers.app]
+image = "busybox:latest"
+command = ["/bin/report"]
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+
+        assert_eq!(resources.len(), 1);
+
+        let cronjob = &resources[0];
+        assert_eq!(cronjob["apiVersion"], "batch/v1");
+        assert_eq!(cronjob["kind"], "CronJob");
+        assert_eq!(cronjob["spec"]["schedule"], "0 2 * * *");
+        assert_eq!(cronjob["spec"]["timeZone"], "Etc/UTC");
+        assert_eq!(cronjob["spec"]["concurrencyPolicy"], "Forbid");
+        assert_eq!(cronjob["spec"]["successfulJobsHistoryLimit"], 3);
+
+        let job_spec = &cronjob["spec"]["jobTemplate"]["spec"];
+        assert_eq!(job_spec["backoffLimit"], 6);
+        assert_eq!(job_spec["ttlSecondsAfterFinished"], 600);
+        assert_eq!(job_spec["template"]["spec"]["restartPolicy"], "OnFailure");
+    }
+
+    #[test]
+    fn test_job_generation_with_hook_annotations() {
+        let toml_content = r#"
+[controllers.migrate]
+type = "job"
+
+[controllers.migrate.annotations]
+"helm.sh/hook" = "pre-upgrade"
+"argocd.argoproj.io/hook" = "PreSync"
+
+[controllers.migrate.job]
+backoffLimit = 2
+completions = 1
+ttlSecondsAfterFinished = 300
+
+[controllers.migrate.containers.app]
+image = "myapp:latest"
+args = ["migrate"]
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+
+        assert_eq!(resources.len(), 1);
+
+        let job = &resources[0];
+        assert_eq!(job["apiVersion"], "batch/v1");
+        assert_eq!(job["kind"], "Job");
+        assert_eq!(
+            job["metadata"]["annotations"]["helm.sh/hook"],
+            "pre-upgrade"
+        );
+        assert_eq!(
+            job["metadata"]["annotations"]["argocd.argoproj.io/hook"],
+            "PreSync"
+        );
+        assert_eq!(job["spec"]["backoffLimit"], 2);
+        assert_eq!(job["spec"]["completions"], 1);
+        assert_eq!(job["spec"]["ttlSecondsAfterFinished"], 300);
+        assert_eq!(job["spec"]["template"]["spec"]["restartPolicy"], "Never");
+    }
+
+    #[test]
+    fn test_daemonset_generation() {
+        let toml_content = r#"
+[controllers.agent]
+type = "daemonset"
+
+[controllers.agent.rollingUpdate]
+unavailable = "1"
+surge = "0"
+
+[controllers.agent.podOptions]
+hostNetwork = true
+hostPID = true
+nodeSelector = { "kubernetes.io/os" = "linux" }
+tolerations = [{ operator = "Exists", effect = "NoSchedule" }]
+
+[controllers.agent.containers.exporter]
+image = "prom/node-exporter:v1.8.2"
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+
+        assert_eq!(resources.len(), 1);
+
+        let daemonset = &resources[0];
+        assert_eq!(daemonset["apiVersion"], "apps/v1");
+        assert_eq!(daemonset["kind"], "DaemonSet");
+
+        let update_strategy = &daemonset["spec"]["updateStrategy"];
+        assert_eq!(update_strategy["type"], "RollingUpdate");
+        assert_eq!(update_strategy["rollingUpdate"]["maxUnavailable"], 1);
+        assert_eq!(update_strategy["rollingUpdate"]["maxSurge"], 0);
+
+        let pod_spec = &daemonset["spec"]["template"]["spec"];
+        assert_eq!(pod_spec["hostNetwork"], true);
+        assert_eq!(pod_spec["hostPID"], true);
+        assert_eq!(pod_spec["nodeSelector"]["kubernetes.io/os"], "linux");
+        assert_eq!(pod_spec["tolerations"][0]["operator"], "Exists");
+    }
+
+    #[test]
+    fn test_persistence_volumes_and_global_mounts() {
+        let toml_content = r#"
+[controllers.main.containers.app]
+image = "nginx:latest"
+
+[[controllers.main.containers.app.volumeMounts]]
+name = "data"
+mountPath = "/app/data"
+
+[controllers.main.containers.sidecar]
+image = "busybox:latest"
+
+[controllers.worker.containers.app]
+image = "busybox:latest"
+
+[configMaps.config.data]
+"app.conf" = "debug=false"
+
+[persistence.data]
+size = "1Gi"
+
+[persistence.config]
+type = "configMap"
+identifier = "config"
+
+[[persistence.config.globalMounts]]
+mountPath = "/etc/app"
+readOnly = true
+
+[persistence.scratch]
+type = "emptyDir"
+
+[[persistence.scratch.globalMounts]]
+mountPath = "/tmp"
+controllers = ["main"]
+containers = ["sidecar"]
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+
+        let pod_spec = |name: &str| {
+            let deployment = resources
+                .iter()
+                .find(|r| r["kind"] == "Deployment" && r["metadata"]["name"] == name)
+                .unwrap();
+            &deployment["spec"]["template"]["spec"]
+        };
+
+        let main = pod_spec("main");
+        let volumes = main["volumes"].as_array().unwrap();
+        assert_eq!(volumes.len(), 3);
+        assert_eq!(volumes[0]["name"], "data");
+        assert_eq!(volumes[0]["persistentVolumeClaim"]["claimName"], "data");
+        assert_eq!(volumes[1]["name"], "config");
+        assert_eq!(volumes[1]["configMap"]["name"], "config");
+        assert_eq!(volumes[2]["name"], "scratch");
+        assert!(volumes[2]["emptyDir"].is_object());
+
+        let app_mounts = main["containers"][0]["volumeMounts"].as_array().unwrap();
+        assert_eq!(app_mounts.len(), 2);
+        assert_eq!(app_mounts[0]["mountPath"], "/app/data");
+        assert_eq!(app_mounts[1]["mountPath"], "/etc/app");
+        assert_eq!(app_mounts[1]["readOnly"], true);
+
+        let sidecar_mounts = main["containers"][1]["volumeMounts"].as_array().unwrap();
+        assert_eq!(sidecar_mounts.len(), 2);
+        assert_eq!(sidecar_mounts[1]["name"], "scratch");
+
+        // The worker only receives the unrestricted global mount
+        let worker = pod_spec("worker");
+        assert_eq!(worker["volumes"].as_array().unwrap().len(), 1);
+        assert_eq!(worker["volumes"][0]["name"], "config");
+    }
+
+    #[test]
+    fn test_additional_persistence_types() {
+        let toml_content = r#"
+[controllers.main.containers.app]
+image = "nginx:latest"
+
+[persistence.cache]
+type = "emptyDir"
+medium = "Memory"
+sizeLimit = "256Mi"
+globalMounts = [{ mountPath = "/cache" }]
+
+[persistence.logs]
+type = "hostPath"
+hostPath = "/var/log"
+hostPathType = "Directory"
+globalMounts = [{ mountPath = "/host/logs", readOnly = true }]
+
+[persistence.shared]
+type = "nfs"
+server = "nfs.example.com"
+path = "/exports/shared"
+globalMounts = [{ mountPath = "/shared" }]
+
+[persistence.models]
+type = "image"
+image = "registry.example.com/models:v1"
+pullPolicy = "IfNotPresent"
+globalMounts = [{ mountPath = "/models" }]
+
+[persistence.tls]
+type = "secret"
+name = "existing-tls"
+defaultMode = 256
+globalMounts = [{ mountPath = "/tls" }]
+
+[persistence.scratch]
+type = "ephemeral"
+size = "5Gi"
+storageClass = "local-path"
+globalMounts = [{ mountPath = "/scratch" }]
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+
+        // Only pvc entries produce standalone PersistentVolumeClaims
+        assert_eq!(resources.len(), 1);
+
+        let volumes = &resources[0]["spec"]["template"]["spec"]["volumes"];
+        assert_eq!(volumes.as_array().unwrap().len(), 6);
+        assert_eq!(volumes[0]["emptyDir"]["medium"], "Memory");
+        assert_eq!(volumes[0]["emptyDir"]["sizeLimit"], "256Mi");
+        assert_eq!(volumes[1]["hostPath"]["path"], "/var/log");
+        assert_eq!(volumes[1]["hostPath"]["type"], "Directory");
+        assert_eq!(volumes[2]["nfs"]["server"], "nfs.example.com");
+        assert_eq!(volumes[2]["nfs"]["path"], "/exports/shared");
+        assert_eq!(
+            volumes[3]["image"]["reference"],
+            "registry.example.com/models:v1"
+        );
+        assert_eq!(volumes[4]["secret"]["secretName"], "existing-tls");
+        assert_eq!(volumes[4]["secret"]["defaultMode"], 256);
+
+        let claim = &volumes[5]["ephemeral"]["volumeClaimTemplate"]["spec"];
+        assert_eq!(claim["storageClassName"], "local-path");
+        assert_eq!(claim["resources"]["requests"]["storage"], "5Gi");
+
+        // A misspelt type is reported even before anything mounts the entry
+        let unmounted: Values = toml::from_str(
+            r#"
+[persistence.tmp]
+type = "emptydir"
+"#,
+        )
+        .unwrap();
+        let error = generate_all_resources(&unmounted).unwrap_err().to_string();
+        assert!(
+            error.contains("persistence.tmp.type: unknown type \"emptydir\""),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_secret_generation() {
+        let toml_content = r#"
+[secrets.app.data]
+password = "hunter2"
+
+[secrets.app.stringData]
+username = "admin"
+
+[secrets.encoded]
+encoded = true
+
+[secrets.encoded.data]
+token = "c2VjcmV0"
+# Wrapped at 76 characters, as `base64` writes it
+cert = """
+LS0tLS1CRUdJTiBDRVJUSUZJQ0FURS0tLS0tCk1JSUJzekNDQVZtZ0F3SUJBZ0lVRXhhbXBsZUNl
+cnRpZmljYXRlQm9keQotLS0tLUVORCBDRVJUSUZJQ0FURS0tLS0tCg==
+"""
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        generators::secret::validate(&values).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0]["kind"], "Secret");
+        assert_eq!(resources[0]["type"], "Opaque");
+        assert_eq!(resources[0]["data"]["password"], "aHVudGVyMg==");
+        assert_eq!(resources[0]["stringData"]["username"], "admin");
+        assert_eq!(resources[1]["data"]["token"], "c2VjcmV0");
+        assert_eq!(
+            resources[1]["data"]["cert"],
+            "LS0tLS1CRUdJTiBDRVJUSUZJQ0FURS0tLS0tCk1JSUJzekNDQVZtZ0F3SUJBZ0lVRXhhbXBsZUNlcnRpZmljYXRlQm9keQotLS0tLUVORCBDRVJUSUZJQ0FURS0tLS0tCg=="
+        );
+    }
+
+    #[test]
+    fn test_secret_validation() {
+        let invalid = [
+            r#"
+[secrets.tls]
+type = "kubernetes.io/tls"
+data = { "tls.crt" = "cert" }
+"#,
+            r#"
+[secrets.registry]
+type = "kubernetes.io/dockerconfigjson"
+stringData = { ".dockerconfigjson" = "not json" }
+"#,
+            r#"
+[secrets.encoded]
+encoded = true
+data = { token = "not base64!" }
+"#,
+        ];
+
+        for toml_content in invalid {
+            let values: Values = toml::from_str(toml_content).unwrap();
+            assert!(generators::secret::validate(&values).is_err());
+        }
+
+        let values: Values = toml::from_str(
+            r#"
+[secrets.registry]
+type = "kubernetes.io/dockerconfigjson"
+stringData = { ".dockerconfigjson" = '{"auths": {}}' }
+"#,
+        )
+        .unwrap();
+        assert!(generators::secret::validate(&values).is_ok());
+    }
+
+    #[test]
+    fn test_ingress_generation() {
+        let toml_content = r#"
+[controllers.main.containers.app]
+image = "nginx:latest"
+
+[service.main.ports.http]
+port = 8080
+
+[service.main.ports.metrics]
+port = 9090
+
+[ingress.main]
+className = "nginx"
+
+[[ingress.main.hosts]]
+host = "app.example.com"
+
+[[ingress.main.hosts.paths]]
+path = "/"
+service = { identifier = "main" }
+
+[[ingress.main.hosts.paths]]
+path = "/metrics"
+pathType = "Exact"
+service = { identifier = "main", port = 9090 }
+
+[[ingress.main.hosts.paths]]
+path = "/legacy"
+service = { name = "legacy-app", port = 80 }
+
+[[ingress.main.tls]]
+secretName = "app-tls"
+hosts = ["app.example.com"]
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+
+        let ingress = resources
+            .iter()
+            .find(|r| r["kind"] == "Ingress")
+            .expect("Ingress should be generated");
+
+        assert_eq!(ingress["apiVersion"], "networking.k8s.io/v1");
+        assert_eq!(ingress["spec"]["ingressClassName"], "nginx");
+        assert_eq!(ingress["spec"]["tls"][0]["secretName"], "app-tls");
+
+        let paths = &ingress["spec"]["rules"][0]["http"]["paths"];
+        assert_eq!(paths[0]["pathType"], "Prefix");
+        assert_eq!(paths[0]["backend"]["service"]["name"], "main");
+        assert_eq!(paths[0]["backend"]["service"]["port"]["number"], 8080);
+        assert_eq!(paths[1]["pathType"], "Exact");
+        assert_eq!(paths[1]["backend"]["service"]["port"]["number"], 9090);
+        assert_eq!(paths[2]["backend"]["service"]["name"], "legacy-app");
+
+        // A path that cannot be resolved is an error, not an empty rule
+        let unresolved = format!(
+            "{}{}",
+            toml_content,
+            r#"
+[service.headless]
+controller = "main"
+
+[[ingress.main.hosts.paths]]
+path = "/headless"
+service = { identifier = "headless" }
+
+[[ingress.main.hosts.paths]]
+path = "/gone"
+service = { identifier = "gone" }
+"#
+        );
+        let values: Values = toml::from_str(&unresolved).unwrap();
+        let error = generate_all_resources(&values).unwrap_err().to_string();
+        assert!(
+            error.contains(
+                "ingress.main.hosts[0].paths[3].service.port: service \"headless\" has no ports"
+            ),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains(
+                "ingress.main.hosts[0].paths[4].service.identifier: service \"gone\" is not defined"
+            ),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_http_route_generation() {
+        let toml_content = r#"
+[service.main.ports.http]
+port = 8080
+
+[route.main]
+hosts = ["app.example.com"]
+
+[[route.main.parentRefs]]
+name = "public"
+namespace = "gateway-system"
+sectionName = "https"
+
+[[route.main.rules]]
+matches = [{ path = { value = "/api" }, method = "GET" }]
+backendRefs = [{ identifier = "main" }, { name = "legacy", port = 80, weight = 10 }]
+timeouts = { request = "10s" }
+
+[[route.main.rules.filters]]
+type = "RequestHeaderModifier"
+requestHeaderModifier = { set = [{ name = "X-Env", value = "prod" }] }
+
+[[route.main.rules.filters]]
+type = "RequestMirror"
+requestMirror = { backendRef = { identifier = "main" } }
+
+[[route.main.rules.filters]]
+type = "URLRewrite"
+urlRewrite = { path = { type = "ReplacePrefixMatch", replacePrefixMatch = "/" } }
+
+[[route.main.tls]]
+hosts = ["www.example.com"]
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+
+        let route = resources
+            .iter()
+            .find(|r| r["kind"] == "HTTPRoute")
+            .expect("HTTPRoute should be generated");
+
+        assert_eq!(route["apiVersion"], "gateway.networking.k8s.io/v1");
+
+        let parent = &route["spec"]["parentRefs"][0];
+        assert_eq!(parent["name"], "public");
+        assert_eq!(parent["namespace"], "gateway-system");
+        assert_eq!(parent["sectionName"], "https");
+
+        let hostnames = route["spec"]["hostnames"].as_array().unwrap();
+        assert_eq!(hostnames.len(), 2);
+
+        let rule = &route["spec"]["rules"][0];
+        assert_eq!(rule["matches"][0]["path"]["type"], "PathPrefix");
+        assert_eq!(rule["matches"][0]["method"], "GET");
+        assert_eq!(rule["backendRefs"][0]["name"], "main");
+        assert_eq!(rule["backendRefs"][0]["port"], 8080);
+        assert_eq!(rule["backendRefs"][1]["weight"], 10);
+        assert_eq!(rule["timeouts"]["request"], "10s");
+
+        let filters = rule["filters"].as_array().unwrap();
+        assert_eq!(filters.len(), 3);
+        assert_eq!(
+            filters[0]["requestHeaderModifier"]["set"][0]["name"],
+            "X-Env"
+        );
+        assert_eq!(filters[1]["requestMirror"]["backendRef"]["name"], "main");
+        assert_eq!(filters[1]["requestMirror"]["backendRef"]["port"], 8080);
+        assert_eq!(filters[2]["urlRewrite"]["path"]["replacePrefixMatch"], "/");
+
+        let mut values = values;
+        let route_config = values.route.get_mut("main").unwrap();
+        route_config.parent_refs.clear();
+        route_config.class_name = Some("internal".to_string());
+        let resources = generate_all_resources(&values).unwrap();
+        let route = resources.iter().find(|r| r["kind"] == "HTTPRoute").unwrap();
+        assert_eq!(route["spec"]["parentRefs"][0]["name"], "internal");
+
+        values.route.get_mut("main").unwrap().class_name = None;
+        let error = generate_all_resources(&values).unwrap_err().to_string();
+        assert!(error.contains("route.main.parentRefs"), "{}", error);
+
+        let unresolved: Values = toml::from_str(
+            r#"
+[route.main]
+className = "public"
+
+[[route.main.rules]]
+backendRefs = [{ identifier = "gone" }]
+filters = [{ type = "RequestMirror", requestMirror = { backendRef = { identifier = "gone" } } }]
+"#,
+        )
+        .unwrap();
+        let error = generate_all_resources(&unresolved).unwrap_err().to_string();
+        for problem in [
+            "route.main.rules[0].backendRefs[0].identifier: service \"gone\" is not defined",
+            "route.main.rules[0].filters[0].requestMirror.backendRef.identifier: \
+             service \"gone\" is not defined",
+        ] {
+            assert!(
+                error.contains(problem),
+                "{} missing from {}",
+                problem,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_network_policy_generation() {
+        let toml_content = r#"
+[controllers.web]
+type = "deployment"
+
+[controllers.web.containers.main]
+image = "nginx"
+
+[controllers.api]
+type = "deployment"
+
+[controllers.api.containers.main]
+image = "api"
+
+[networkpolicies.api]
+controller = "api"
+policyTypes = ["Ingress", "Egress"]
+
+[[networkpolicies.api.ingress]]
+from = [
+  { controller = "web" },
+  { namespaceSelector = { matchLabels = { "kubernetes.io/metadata.name" = "monitoring" } } },
+]
+ports = [{ protocol = "TCP", port = 8080 }]
+
+[[networkpolicies.api.egress]]
+to = [{ ipBlock = { cidr = "10.0.0.0/8" } }]
+ports = [{ port = "dns", protocol = "UDP" }]
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+
+        let policy = resources
+            .iter()
+            .find(|r| r["kind"] == "NetworkPolicy")
+            .expect("NetworkPolicy should be generated");
+
+        assert_eq!(policy["apiVersion"], "networking.k8s.io/v1");
+        assert_eq!(policy["metadata"]["name"], "api");
+
+        let spec = &policy["spec"];
+        assert_eq!(
+            spec["podSelector"]["matchLabels"]["app.kubernetes.io/controller"],
+            "api"
+        );
+        assert_eq!(spec["policyTypes"].as_array().unwrap().len(), 2);
+
+        let from = &spec["ingress"][0]["from"];
+        assert_eq!(
+            from[0]["podSelector"]["matchLabels"]["app.kubernetes.io/controller"],
+            "web"
+        );
+        assert!(from[0].get("controller").is_none());
+        assert_eq!(
+            from[1]["namespaceSelector"]["matchLabels"]["kubernetes.io/metadata.name"],
+            "monitoring"
+        );
+        assert_eq!(spec["ingress"][0]["ports"][0]["port"], 8080);
+
+        assert_eq!(spec["egress"][0]["to"][0]["ipBlock"]["cidr"], "10.0.0.0/8");
+        assert_eq!(spec["egress"][0]["ports"][0]["port"], "dns");
+
+        let conflicting = toml_content.replace(
+            "controller = \"api\"\n",
+            "controller = \"api\"\npodSelector = { matchLabels = { tier = \"api\" } }\n",
+        );
+        let values: Values = toml::from_str(&conflicting).unwrap();
+        let error = generate_all_resources(&values).unwrap_err().to_string();
+        assert!(
+            error.contains("networkpolicies.api.podSelector: conflicts with controller"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_rbac_generation() {
+        let toml_content = r#"
+[serviceAccount.app]
+
+[rbac.roles.reader]
+[[rbac.roles.reader.rules]]
+apiGroups = [""]
+resources = ["pods", "configmaps"]
+verbs = ["get", "list", "watch"]
+
+[rbac.roles.node-reader]
+clusterWide = true
+[[rbac.roles.node-reader.rules]]
+apiGroups = [""]
+resources = ["nodes"]
+verbs = ["get"]
+
+[rbac.bindings.reader]
+roleRef = { kind = "Role", name = "reader" }
+subjects = [
+  { kind = "ServiceAccount", identifier = "app" },
+  { kind = "User", name = "jane", apiGroup = "rbac.authorization.k8s.io" },
+]
+
+[rbac.bindings.node-reader]
+clusterWide = true
+roleRef = { kind = "ClusterRole", name = "node-reader" }
+subjects = [{ kind = "ServiceAccount", identifier = "app", namespace = "apps" }]
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+
+        let role = resources
+            .iter()
+            .find(|r| r["kind"] == "Role")
+            .expect("Role should be generated");
+        assert_eq!(role["apiVersion"], "rbac.authorization.k8s.io/v1");
+        assert_eq!(role["metadata"]["name"], "reader");
+        assert_eq!(role["rules"][0]["verbs"].as_array().unwrap().len(), 3);
+        assert!(role["rules"][0].get("resourceNames").is_none());
+
+        let cluster_role = resources
+            .iter()
+            .find(|r| r["kind"] == "ClusterRole")
+            .expect("ClusterRole should be generated");
+        assert_eq!(cluster_role["metadata"]["name"], "node-reader");
+
+        let binding = resources
+            .iter()
+            .find(|r| r["kind"] == "RoleBinding")
+            .expect("RoleBinding should be generated");
+        assert_eq!(binding["roleRef"]["apiGroup"], "rbac.authorization.k8s.io");
+        assert_eq!(binding["roleRef"]["name"], "reader");
+        assert_eq!(binding["subjects"][0]["kind"], "ServiceAccount");
+        assert_eq!(binding["subjects"][0]["name"], "app");
+        assert_eq!(binding["subjects"][1]["name"], "jane");
+
+        let cluster_binding = resources
+            .iter()
+            .find(|r| r["kind"] == "ClusterRoleBinding")
+            .expect("ClusterRoleBinding should be generated");
+        assert_eq!(cluster_binding["subjects"][0]["name"], "app");
+        assert_eq!(cluster_binding["subjects"][0]["namespace"], "apps");
+
+        // A cluster-wide subject needs a namespace from somewhere
+        let unresolved = toml_content.replace(", namespace = \"apps\"", "");
+        let mut values: Values = toml::from_str(&unresolved).unwrap();
+        let error = generate_all_resources(&values).unwrap_err().to_string();
+        assert!(error.contains("rbac.bindings.node-reader.subjects[0].namespace"));
+
+        values.global.namespace = Some("apps".to_string());
+        let resources = generate_all_resources(&values).unwrap();
+        let cluster_binding = resources
+            .iter()
+            .find(|r| r["kind"] == "ClusterRoleBinding")
+            .unwrap();
+        assert_eq!(cluster_binding["subjects"][0]["namespace"], "apps");
+
+        // An unknown subject is reported rather than left out of the binding
+        let unknown = toml_content.replace("identifier = \"app\" }", "identifier = \"ap\" }");
+        let values: Values = toml::from_str(&unknown).unwrap();
+        let error = generate_all_resources(&values).unwrap_err().to_string();
+        assert!(
+            error.contains(
+                "rbac.bindings.reader.subjects[0].identifier: serviceAccount \"ap\" is not defined"
+            ),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_service_monitor_generation() {
+        let toml_content = r#"
+[controllers.main.containers.app]
+image = "app:latest"
+
+[service.main.ports.http]
+port = 8080
+
+[service.main.ports.metrics]
+port = 9090
+
+[serviceMonitor.main]
+path = "/metrics"
+endpoints = [{ port = "metrics" }, { port = "http", path = "/stats", interval = "10s" }]
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        assert!(references::check(&values).is_empty());
+        let resources = generate_all_resources(&values).unwrap();
+
+        let monitor = resources
+            .iter()
+            .find(|r| r["kind"] == "ServiceMonitor")
+            .expect("ServiceMonitor should be generated");
+
+        assert_eq!(monitor["apiVersion"], "monitoring.coreos.com/v1");
+        assert_eq!(monitor["metadata"]["name"], "main");
+
+        let service = resources.iter().find(|r| r["kind"] == "Service").unwrap();
+        let match_labels = monitor["spec"]["selector"]["matchLabels"]
+            .as_object()
+            .unwrap();
+        assert_eq!(match_labels["app.kubernetes.io/service"], "main");
+        for (key, value) in match_labels {
+            assert_eq!(&service["metadata"]["labels"][key], value);
+        }
+
+        let endpoints = &monitor["spec"]["endpoints"];
+        assert_eq!(endpoints[0]["port"], "metrics");
+        assert_eq!(endpoints[0]["path"], "/metrics");
+        assert_eq!(endpoints[0]["interval"], "30s");
+        assert_eq!(endpoints[1]["path"], "/stats");
+        assert_eq!(endpoints[1]["interval"], "10s");
+
+        let invalid: Values = toml::from_str(
+            r#"
+[service.main.ports.http]
+port = 8080
+
+[serviceMonitor.main]
+endpoints = [{ port = "metrics" }]
+"#,
+        )
+        .unwrap();
+        let problems = references::check(&invalid);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(
+            problems[0].to_string(),
+            "serviceMonitor.main.endpoints[0].port: service \"main\" has no port named \"metrics\""
+        );
+
+        // A monitor without its Service is reported, not left out
+        let mut missing = invalid;
+        missing.service.clear();
+        let error = generate_all_resources(&missing).unwrap_err().to_string();
+        assert!(
+            error.contains("serviceMonitor.main.service: service \"main\" is not defined"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_raw_resource_generation() {
+        let toml_content = r#"
+[global.labels]
+team = "platform"
+
+[global.annotations]
+owner = "ops"
+
+[rawResources.limits]
+[rawResources.limits.spec]
+apiVersion = "v1"
+kind = "LimitRange"
+metadata = { name = "limits", labels = { team = "apps" } }
+spec = { limits = [{ type = "Container", default = { cpu = "500m" } }] }
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        generators::raw_resource::validate(&values).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+        generators::raw_resource::validate_unique(&values, &resources).unwrap();
+
+        assert_eq!(resources.len(), 1);
+        let raw = &resources[0];
+        assert_eq!(raw["kind"], "LimitRange");
+        assert_eq!(raw["metadata"]["name"], "limits");
+        assert_eq!(raw["metadata"]["labels"]["team"], "apps");
+        assert_eq!(raw["metadata"]["annotations"]["owner"], "ops");
+        assert_eq!(raw["spec"]["limits"][0]["default"]["cpu"], "500m");
+
+        let missing_kind: Values = toml::from_str(
+            r#"
+[rawResources.broken.spec]
+apiVersion = "v1"
+metadata = { name = "broken" }
+"#,
+        )
+        .unwrap();
+        assert!(generators::raw_resource::validate(&missing_kind).is_err());
+
+        let duplicate: Values = toml::from_str(
+            r#"
+[configMaps.settings.data]
+key = "value"
+
+[rawResources.settings.spec]
+apiVersion = "v1"
+kind = "ConfigMap"
+metadata = { name = "settings" }
+"#,
+        )
+        .unwrap();
+        let resources = generate_all_resources(&duplicate).unwrap();
+        assert!(generators::raw_resource::validate_unique(&duplicate, &resources).is_err());
+
+        // Still a duplicate once both are stamped with the release namespace
+        let mut namespaced = duplicate;
+        namespaced.global.namespace = Some("prod".to_string());
+        let resources = generate_all_resources(&namespaced).unwrap();
+        assert_eq!(resources[0]["metadata"]["namespace"], "prod");
+        assert!(generators::raw_resource::validate_unique(&namespaced, &resources).is_err());
+    }
+
+    #[test]
+    fn test_raw_resource_release_name() {
+        let mut values_file = NamedTempFile::new().unwrap();
+        write!(
+            values_file,
+            r#"
+[rawResources.limits.spec]
+apiVersion = "v1"
+kind = "LimitRange"
+metadata = {{ name = "limits" }}
+
+[rawResources.widgets.spec]
+apiVersion = "apiextensions.k8s.io/v1"
+kind = "CustomResourceDefinition"
+metadata = {{ name = "widgets.example.com" }}
+"#
+        )
+        .unwrap();
+        let path = values_file.path().to_str().unwrap();
+
+        let options = RenderOptions {
+            name: Some("shop".to_string()),
+            ..Default::default()
+        };
+        let resources = render_values_file(path, &options).unwrap();
+        let name_of = |kind: &str| {
+            resources.iter().find(|r| r["kind"] == kind).unwrap()["metadata"]["name"].clone()
+        };
+        assert_eq!(name_of("LimitRange"), "shop-limits");
+        // A CRD must be named <plural>.<group>
+        assert_eq!(name_of("CustomResourceDefinition"), "widgets.example.com");
+    }
+
+    #[test]
+    fn test_pod_options_mapping() {
+        let toml_content = r#"
+[secrets.registry]
+type = "kubernetes.io/dockerconfigjson"
+stringData = { ".dockerconfigjson" = '{"auths": {}}' }
+
+[defaultPodOptions]
+imagePullSecrets = ["registry", "external"]
+
+[controllers.main.containers.app]
+image = "app:latest"
+
+[controllers.main.podOptions]
+hostUsers = false
+shareProcessNamespace = true
+securityContext = { runAsNonRoot = true, fsGroup = 1000 }
+dnsConfig = { nameservers = ["1.1.1.1"], options = [{ name = "ndots", value = "2" }] }
+hostAliases = [{ ip = "10.0.0.1", hostnames = ["db.local"] }]
+tolerations = [{ key = "dedicated", operator = "Equal", value = "app", effect = "NoSchedule" }]
+topologySpreadConstraints = [{ maxSkew = 1, topologyKey = "kubernetes.io/hostname", whenUnsatisfiable = "DoNotSchedule" }]
+
+[controllers.main.podOptions.affinity.nodeAffinity.requiredDuringSchedulingIgnoredDuringExecution]
+nodeSelectorTerms = [{ matchExpressions = [{ key = "zone", operator = "In", values = ["a"] }] }]
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        generators::pod::validate(&values).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+
+        let deployment = resources
+            .iter()
+            .find(|r| r["kind"] == "Deployment")
+            .unwrap();
+        let spec = &deployment["spec"]["template"]["spec"];
+
+        assert_eq!(spec["hostUsers"], false);
+        assert_eq!(spec["shareProcessNamespace"], true);
+        assert_eq!(spec["securityContext"]["fsGroup"], 1000);
+        assert_eq!(spec["dnsConfig"]["options"][0]["value"], "2");
+        assert_eq!(spec["hostAliases"][0]["hostnames"][0], "db.local");
+        assert_eq!(spec["tolerations"][0]["effect"], "NoSchedule");
+        assert_eq!(spec["topologySpreadConstraints"][0]["maxSkew"], 1);
+        assert_eq!(
+            spec["affinity"]["nodeAffinity"]["requiredDuringSchedulingIgnoredDuringExecution"]["nodeSelectorTerms"]
+                [0]["matchExpressions"][0]["key"],
+            "zone"
+        );
+        assert_eq!(spec["imagePullSecrets"][0]["name"], "registry");
+        assert_eq!(spec["imagePullSecrets"][1]["name"], "external");
+
+        let invalid: Values = toml::from_str(
+            r#"
+[controllers.main.containers.app]
+image = "app:latest"
+
+[controllers.main.podOptions]
+tolerations = [{ key = "dedicated", tolerationSeconds = "forever" }]
+"#,
+        )
+        .unwrap();
+        let error = generators::pod::validate(&invalid).unwrap_err().to_string();
+        assert!(error.contains("controllers.main.podOptions.tolerations[0]"));
+    }
+
+    #[test]
+    fn test_pod_options_merge_strategy() {
+        let toml_content = r#"
+defaultPodOptionsStrategy = "merge"
+
+[defaultPodOptions]
+enableServiceLinks = false
+nodeSelector = { "kubernetes.io/os" = "linux", zone = "a" }
+tolerations = [{ key = "default", operator = "Exists" }]
+securityContext = { runAsNonRoot = true, seccompProfile = { type = "RuntimeDefault" } }
+
+[controllers.main.containers.app]
+image = "app:latest"
+
+[controllers.main.podOptions]
+enableServiceLinks = true
+nodeSelector = { zone = "b" }
+tolerations = [{ key = "dedicated", operator = "Exists" }]
+securityContext = { fsGroup = 1000 }
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        generators::pod::validate(&values).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+        let spec = &resources[0]["spec"]["template"]["spec"];
+
+        assert_eq!(spec["enableServiceLinks"], true);
+        assert_eq!(spec["nodeSelector"]["kubernetes.io/os"], "linux");
+        assert_eq!(spec["nodeSelector"]["zone"], "b");
+        assert_eq!(spec["tolerations"].as_array().unwrap().len(), 2);
+        assert_eq!(spec["securityContext"]["runAsNonRoot"], true);
+        assert_eq!(
+            spec["securityContext"]["seccompProfile"]["type"],
+            "RuntimeDefault"
+        );
+        assert_eq!(spec["securityContext"]["fsGroup"], 1000);
+
+        let replace: Values = toml::from_str(&format!(
+            "defaultPodOptionsListStrategy = \"replace\"\n{}",
+            toml_content
+        ))
+        .unwrap();
+        let resources = generate_all_resources(&replace).unwrap();
+        let tolerations = &resources[0]["spec"]["template"]["spec"]["tolerations"];
+        assert_eq!(tolerations.as_array().unwrap().len(), 1);
+        assert_eq!(tolerations[0]["key"], "dedicated");
+
+        let unknown: Values = toml::from_str(
+            r#"
+defaultPodOptionsStrategy = "deep"
+
+[controllers.main.containers.app]
+image = "app:latest"
+"#,
+        )
+        .unwrap();
+        assert!(generators::pod::validate(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_release_name_references() {
+        let toml_content = r#"
+[global]
+releaseName = "blue"
+
+[controllers.main]
+serviceAccount = { identifier = "app" }
+
+[controllers.main.containers.app]
+image = "app:latest"
+env.LEVEL.valueFrom.configMapKeyRef = { name = "settings", key = "level" }
+
+[[controllers.main.containers.app.volumeMounts]]
+name = "data"
+mountPath = "/data"
+
+[service.main.ports.http]
+port = 80
+
+[serviceAccount.app]
+secrets = ["token", "external-token"]
+
+[secrets.token.stringData]
+token = "hunter2"
+
+[configMaps.settings.data]
+level = "info"
+
+[persistence.data]
+size = "1Gi"
+
+[[ingress.main.hosts]]
+host = "app.example.com"
+paths = [{ path = "/", service = { identifier = "main" } }]
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+        let find = |kind: &str| resources.iter().find(|r| r["kind"] == kind).unwrap();
+
+        let deployment = find("Deployment");
+        assert_eq!(deployment["metadata"]["name"], "blue-main");
+        assert_eq!(
+            deployment["metadata"]["labels"]["app.kubernetes.io/instance"],
+            "blue"
+        );
+        let pod_spec = &deployment["spec"]["template"]["spec"];
+        assert_eq!(pod_spec["serviceAccountName"], "blue-app");
+        assert_eq!(
+            pod_spec["containers"][0]["env"][0]["valueFrom"]["configMapKeyRef"]["name"],
+            "blue-settings"
+        );
+        assert_eq!(
+            pod_spec["volumes"][0]["persistentVolumeClaim"]["claimName"],
+            "blue-data"
+        );
+
+        let service = find("Service");
+        assert_eq!(service["metadata"]["name"], "blue-main");
+        assert_eq!(
+            service["spec"]["selector"],
+            deployment["spec"]["selector"]["matchLabels"]
+        );
+
+        let service_account = find("ServiceAccount");
+        assert_eq!(service_account["metadata"]["name"], "blue-app");
+        assert_eq!(service_account["secrets"][0]["name"], "blue-token");
+        assert_eq!(service_account["secrets"][1]["name"], "external-token");
+        assert_eq!(find("ConfigMap")["metadata"]["name"], "blue-settings");
+        assert_eq!(
+            find("Ingress")["spec"]["rules"][0]["http"]["paths"][0]["backend"]["service"]["name"],
+            "blue-main"
+        );
+    }
+
+    #[test]
+    fn test_namespace_stamping() {
+        let toml_content = r#"
+[global]
+namespace = "shop"
+
+[namespace]
+create = true
+labels = { "pod-security.kubernetes.io/enforce" = "restricted" }
+
+[controllers.main.containers.app]
+image = "app:latest"
+
+[serviceAccount.app]
+
+[rbac.roles.nodes]
+clusterWide = true
+rules = [{ apiGroups = [""], resources = ["nodes"], verbs = ["get"] }]
+
+[rbac.bindings.nodes]
+clusterWide = true
+roleRef = { kind = "ClusterRole", name = "nodes" }
+subjects = [{ kind = "ServiceAccount", identifier = "app" }]
+
+[rawResources.quota.spec]
+apiVersion = "v1"
+kind = "ResourceQuota"
+metadata = { name = "quota", namespace = "other" }
+
+[rawResources.policy.spec]
+apiVersion = "kyverno.io/v1"
+kind = "ClusterPolicy"
+metadata = { name = "require-labels", namespace = "" }
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+        let find = |kind: &str| resources.iter().find(|r| r["kind"] == kind).unwrap();
+
+        let namespace = &resources[0];
+        assert_eq!(namespace["kind"], "Namespace");
+        assert_eq!(namespace["metadata"]["name"], "shop");
+        assert_eq!(
+            namespace["metadata"]["labels"]["pod-security.kubernetes.io/enforce"],
+            "restricted"
+        );
+        assert!(namespace["metadata"].get("namespace").is_none());
+
+        assert_eq!(find("Deployment")["metadata"]["namespace"], "shop");
+        assert_eq!(find("ServiceAccount")["metadata"]["namespace"], "shop");
+        assert!(find("ClusterRole")["metadata"].get("namespace").is_none());
+
+        let binding = find("ClusterRoleBinding");
+        assert!(binding["metadata"].get("namespace").is_none());
+        assert_eq!(binding["subjects"][0]["namespace"], "shop");
+
+        assert_eq!(find("ResourceQuota")["metadata"]["namespace"], "other");
+        assert!(find("ClusterPolicy")["metadata"].get("namespace").is_none());
+    }
+
+    #[test]
+    fn test_layered_values_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+
+        write(
+            "base.toml",
+            r#"
+[global.labels]
+team = "platform"
+
+[controllers.main]
+replicas = 1
+
+[controllers.main.containers.app]
+image = "app:1.0"
+env.LOG_LEVEL.value = "debug"
+
+[controllers.worker.containers.app]
+image = "worker:1.0"
+"#,
+        );
+        let prod = write(
+            "prod.toml",
+            r#"
+extends = "base.toml"
+
+[global.labels]
+environment = "production"
+
+[controllers.main]
+replicas = 3
+
+[controllers.main.containers.app.env.LOG_LEVEL]
+value = "warn"
+
+[controllers.worker]
+"$delete" = true
+"#,
+        );
+        let hotfix = write(
+            "hotfix.toml",
+            r#"
+[controllers.main.containers.app]
+image = "app:1.0.1"
+"#,
+        );
+
+        let resources = render_values_files(&[&prod, &hotfix], &RenderOptions::default()).unwrap();
+
+        assert_eq!(resources.len(), 1);
+        let deployment = &resources[0];
+        assert_eq!(deployment["metadata"]["name"], "main");
+        assert_eq!(deployment["metadata"]["labels"]["team"], "platform");
+        assert_eq!(
+            deployment["metadata"]["labels"]["environment"],
+            "production"
+        );
+        assert_eq!(deployment["spec"]["replicas"], 3);
+
+        let container = &deployment["spec"]["template"]["spec"]["containers"][0];
+        assert_eq!(container["image"], "app:1.0.1");
+        assert_eq!(container["env"][0]["value"], "warn");
+
+        let cycle = write("cycle.toml", r#"extends = ["cycle.toml"]"#);
+        let error = render_values_files(&[&cycle], &RenderOptions::default()).unwrap_err();
+        assert!(error.to_string().contains("extends cycle"));
+    }
+
+    #[test]
+    fn test_delete_across_filenames() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+
+        let base = write(
+            "base.toml",
+            r#"
+[controllers.main.containers.app]
+image = "app:1.0"
+
+[controllers.worker.containers.app]
+image = "worker:1.0"
+
+[service.worker.ports.http]
+port = 80
+"#,
+        );
+        let prod = write(
+            "prod.toml",
+            r#"
+[controllers.worker]
+"$delete" = true
+
+[service.worker]
+"$delete" = true
+"#,
+        );
+
+        let resources = render_values_files(&[&base, &prod], &RenderOptions::default()).unwrap();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0]["kind"], "Deployment");
+        assert_eq!(resources[0]["metadata"]["name"], "main");
+    }
+
+    #[test]
+    fn test_set_overrides() {
+        let mut values_file = NamedTempFile::new().unwrap();
+        write!(
+            values_file,
+            r#"
+[controllers.main]
+replicas = 1
+
+[controllers.main.containers.app]
+image = "nginx:1.25"
+
+[configMaps.settings.data]
+level = "info"
+"#
+        )
+        .unwrap();
+        let mut script = NamedTempFile::new().unwrap();
+        write!(script, "echo hello").unwrap();
+
+        let options = RenderOptions {
+            set: vec![
+                "controllers.main.containers.app.image=nginx:1.27".to_string(),
+                "controllers.main.replicas=3".to_string(),
+                r#"global.labels."app.kubernetes.io/part-of"=shop"#.to_string(),
+            ],
+            set_string: vec!["configMaps.settings.data.level=42".to_string()],
+            set_file: vec![format!(
+                "configMaps.settings.data.\"run.sh\"={}",
+                script.path().display()
+            )],
+            ..Default::default()
+        };
+        let path = values_file.path().to_str().unwrap();
+        let resources = render_values_file(path, &options).unwrap();
+
+        let deployment = resources
+            .iter()
+            .find(|r| r["kind"] == "Deployment")
+            .unwrap();
+        assert_eq!(deployment["spec"]["replicas"], 3);
+        assert_eq!(
+            deployment["spec"]["template"]["spec"]["containers"][0]["image"],
+            "nginx:1.27"
+        );
+        assert_eq!(
+            deployment["metadata"]["labels"]["app.kubernetes.io/part-of"],
+            "shop"
+        );
+
+        let config_map = resources.iter().find(|r| r["kind"] == "ConfigMap").unwrap();
+        assert_eq!(config_map["data"]["level"], "42");
+        assert_eq!(config_map["data"]["run.sh"], "echo hello");
+
+        let bad_path = RenderOptions {
+            set: vec!["controllers.main.replicas.count=2".to_string()],
+            ..Default::default()
+        };
+        let error = render_values_file(path, &bad_path).unwrap_err().to_string();
+        assert!(
+            error.contains("nearest valid parent controllers.main.replicas is an integer"),
+            "{}",
+            error
+        );
+
+        let missing_array = RenderOptions {
+            set: vec!["controllers.main.containers.app.args[0]=x".to_string()],
+            ..Default::default()
+        };
+        let error = render_values_file(path, &missing_array)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains(
+                "nearest valid parent controllers.main.containers.app is a table without args, \
+                 expected an array containing the index"
+            ),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_rejected() {
+        let mut values_file = NamedTempFile::new().unwrap();
+        write!(
+            values_file,
+            r#"[controllers.main]
+replica = 3
+
+[controllers.main.containers.app]
+image = "nginx:1.25"
+ports = {{ http = {{ containerPort = 80, protocl = "TCP" }} }}
+"#
+        )
+        .unwrap();
+        let path = values_file.path().to_str().unwrap();
+
+        let options = RenderOptions {
+            set: vec!["global.lables.team=web".to_string()],
+            ..Default::default()
+        };
+        let error = render_values_file(path, &options).unwrap_err().to_string();
+        assert!(
+            error.contains(&format!(
+                "{}:2:1: unknown key `controllers.main.replica` (did you mean `replicas`?)",
+                path
+            )),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains(&format!(
+                "{}:6:40: unknown key `controllers.main.containers.app.ports.http.protocl` \
+                 (did you mean `protocol`?)",
+                path
+            )),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains("command line: unknown key `global.lables` (did you mean `labels`?)"),
+            "{}",
+            error
+        );
+
+        let lenient = RenderOptions {
+            allow_unknown_keys: true,
+            ..options
+        };
+        let resources = render_values_file(path, &lenient).unwrap();
+        assert_eq!(resources.len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_keys_found_past_missing_fields() {
+        let mut values_file = NamedTempFile::new().unwrap();
+        write!(
+            values_file,
+            r#"[defaultPodOptions]
+hostIpc = true
+
+[controllers.main.containers.app]
+image = "nginx:1.25"
+ports = {{ http = {{ containerport = 80 }} }}
+volumeMounts = [{{ name = "data", mountPath = "/data", mountpth = "/tmp" }}]
+
+[service.main.ports.http]
+port = 80
+targetport = 8080
+"#
+        )
+        .unwrap();
+        let path = values_file.path().to_str().unwrap();
+
+        let error = render_values_file(path, &RenderOptions::default())
+            .unwrap_err()
+            .to_string();
+        for key in [
+            "controllers.main.containers.app.ports.http.containerport` (did you mean `containerPort`?)",
+            "controllers.main.containers.app.volumeMounts[0].mountpth` (did you mean `mountPath`?)",
+            "service.main.ports.http.targetport` (did you mean `targetPort`?)",
+        ] {
+            assert!(error.contains(key), "{}", error);
+        }
+        assert!(!error.contains("hostIpc"), "{}", error);
+        assert!(!error.contains("missing field"), "{}", error);
+    }
+
+    #[test]
+    fn test_generation_errors_are_collected() {
+        let values: Values = toml::from_str(
+            r#"
+[defaultPodOptions]
+tolerations = [{ key = "dedicated", operator = 3 }]
+
+[controllers.main.containers.app]
+image = "app:latest"
+livenessProbe = { httpGet = { port = [80] } }
+securityContext = { runAsUser = "root" }
+
+[persistence.data]
+type = "pvc"
+dataSource = { kind = 3 }
+
+[networkpolicies.web]
+ingress = [{ ports = [{ port = 80, protocol = ["TCP"] }] }]
+"#,
+        )
+        .unwrap();
+
+        let error = generate_all_resources(&values).unwrap_err();
+        let BunkuError::Multiple(errors) = &error else {
+            panic!("expected several errors, got {}", error);
+        };
+        assert_eq!(errors.len(), 5, "{}", error);
+
+        let report = error.to_string();
+        for path in [
+            "defaultPodOptions.tolerations[0].operator: ",
+            "controllers.main.containers.app.securityContext.runAsUser: ",
+            "controllers.main.containers.app.livenessProbe.httpGet.port: ",
+            "persistence.data.dataSource.kind: ",
+            "networkpolicies.web.ingress[0].ports[0].protocol: ",
+        ] {
+            assert!(report.contains(path), "{} missing from {}", path, report);
+        }
+        assert!(report.contains("invalid type"), "{}", report);
+
+        // The merged pod options are checked too, at the options they came from
+        let template =
+            generators::pod::generate_pod_template(&values, "main", &values.controllers["main"]);
+        let report = template.unwrap_err().to_string();
+        assert!(
+            report.contains("defaultPodOptions.tolerations[0].operator: "),
+            "{}",
+            report
+        );
+    }
+
+    #[test]
+    fn test_dangling_references() {
+        // References that still render as written
+        let renderable = r#"
+[controllers.main]
+serviceAccount = { identifier = "runner" }
+
+[controllers.main.containers.app]
+image = "app:latest"
+
+[controllers.old]
+enabled = false
+
+[controllers.old.containers.app]
+image = "app:latest"
+
+[service.main]
+controller = "old"
+
+[service.main.ports.http]
+port = 80
+
+[route.main]
+parentRefs = [{ name = "public" }]
+rules = [{ backendRefs = [{ identifier = "main", port = 8080 }] }]
+"#;
+        // References that would leave a hole in the manifests
+        let unrenderable = r#"
+[controllers.worker.containers.app]
+image = "app:latest"
+volumeMounts = [{ name = "cache", mountPath = "/cache" }]
+
+[[ingress.main.hosts]]
+host = "example.com"
+paths = [{ path = "/", service = { identifier = "api" } }]
+
+[route.internal]
+parentRefs = [{ name = "internal" }]
+rules = [{ backendRefs = [{ identifier = "api" }] }]
+
+[rbac.bindings.reader]
+roleRef = { kind = "ClusterRole", name = "view" }
+subjects = [{ kind = "ServiceAccount", identifier = "runner" }]
+
+[serviceMonitor.api]
+endpoints = [{ port = "metrics" }]
+"#;
+        let warned = [
+            "controllers.main.serviceAccount.identifier: serviceAccount \"runner\" is not defined",
+            "service.main.controller: controller \"old\" is disabled",
+            "route.main.rules[0].backendRefs[0].port: service \"main\" has no port 8080",
+        ];
+        let failed = [
+            "controllers.worker.containers.app.volumeMounts[0].name: persistence \"cache\" is not defined",
+            "ingress.main.hosts[0].paths[0].service.identifier: service \"api\" is not defined",
+            "route.internal.rules[0].backendRefs[0].identifier: service \"api\" is not defined",
+            "rbac.bindings.reader.subjects[0].identifier: serviceAccount \"runner\" is not defined",
+            "serviceMonitor.api.service: service \"api\" is not defined",
+        ];
+
+        let mut values_file = NamedTempFile::new().unwrap();
+        write!(values_file, "{}{}", renderable, unrenderable).unwrap();
+        let path = values_file.path().to_str().unwrap();
+
+        let error = render_values_file(path, &RenderOptions::default())
+            .unwrap_err()
+            .to_string();
+        for problem in warned.iter().chain(&failed) {
+            assert!(
+                error.contains(problem),
+                "{} missing from {}",
+                problem,
+                error
+            );
+        }
+
+        // Downgrading keeps what cannot be rendered an error, reported once
+        let lenient = RenderOptions {
+            reference_warnings: true,
+            ..Default::default()
+        };
+        let report: Vec<(bool, String)> = validate(&[path], &lenient)
+            .into_iter()
+            .map(|d| {
+                let error = d.severity == diagnostics::Severity::Error;
+                (error, format!("{}: {}", d.path.unwrap(), d.message))
+            })
+            .collect();
+        let expected: Vec<(bool, String)> = warned
+            .iter()
+            .map(|problem| (false, problem.to_string()))
+            .chain(failed.iter().map(|problem| (true, problem.to_string())))
+            .collect();
+        for problem in &expected {
+            assert!(
+                report.contains(problem),
+                "{:?} missing from {:?}",
+                problem,
+                report
+            );
+        }
+        assert_eq!(report.len(), expected.len(), "{:?}", report);
+
+        // Without those, every reference renders exactly as written
+        let mut values_file = NamedTempFile::new().unwrap();
+        write!(values_file, "{}", renderable).unwrap();
+        let path = values_file.path().to_str().unwrap();
+        let rendered = render(&[path], &lenient).unwrap();
+        let warnings: Vec<String> = rendered.warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(warnings, warned);
+
+        let find = |kind: &str| {
+            rendered
+                .resources
+                .iter()
+                .find(|r| r["kind"] == kind)
+                .unwrap()
+        };
+        assert_eq!(
+            find("Deployment")["spec"]["template"]["spec"]["serviceAccountName"],
+            "runner"
+        );
+        assert_eq!(
+            find("Service")["spec"]["selector"]["app.kubernetes.io/controller"],
+            "old"
+        );
+        let backend = &find("HTTPRoute")["spec"]["rules"][0]["backendRefs"][0];
+        assert_eq!(backend["name"], "main");
+        assert_eq!(backend["port"], 8080);
+    }
+
+    #[test]
+    fn test_statefulset_mounts_claim_template() {
+        let mut values_file = NamedTempFile::new().unwrap();
+        write!(
+            values_file,
+            r#"
+[controllers.db]
+type = "statefulset"
+
+[[controllers.db.statefulset.volumeClaimTemplates]]
+name = "data"
+size = "10Gi"
+
+[controllers.db.containers.postgres]
+image = "postgres:16"
+volumeMounts = [{{ name = "data", mountPath = "/var/lib/postgresql/data" }}]
+"#
+        )
+        .unwrap();
+        let path = values_file.path().to_str().unwrap();
+
+        let resources = render_values_file(path, &RenderOptions::default()).unwrap();
+        let statefulset = resources
+            .iter()
+            .find(|r| r["kind"] == "StatefulSet")
+            .unwrap();
+        assert_eq!(
+            statefulset["spec"]["template"]["spec"]["containers"][0]["volumeMounts"][0]["name"],
+            "data"
+        );
+        assert_eq!(
+            statefulset["spec"]["volumeClaimTemplates"][0]["metadata"]["name"],
+            "data"
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_locations() {
+        use crate::diagnostics::{Location, Severity};
+
+        let mut values_file = NamedTempFile::new().unwrap();
+        write!(
+            values_file,
+            r#"[controllers.main]
+replica = 3
+serviceAccount = {{ identifier = "runner" }}
+
+[controllers.main.containers.app]
+image = "app:latest"
+livenessProbe = {{ httpGet = {{ port = [80] }} }}
+"#
+        )
+        .unwrap();
+        let path = values_file.path().to_str().unwrap();
+        let at = |line, column| {
+            Some(Location {
+                file: path.to_string(),
+                line,
+                column,
+            })
+        };
+
+        let strict = validate(&[path], &RenderOptions::default());
+        assert_eq!(strict.len(), 1, "{:?}", strict);
+        assert_eq!(strict[0].severity, Severity::Error);
+        assert_eq!(strict[0].location, at(2, 1));
+        assert_eq!(strict[0].path.as_deref(), Some("controllers.main.replica"));
+        assert_eq!(strict[0].message, "unknown key (did you mean `replicas`?)");
+
+        let lenient = RenderOptions {
+            allow_unknown_keys: true,
+            reference_warnings: true,
+            ..Default::default()
+        };
+        let report = validate(&[path], &lenient);
+        assert_eq!(report.len(), 2, "{:?}", report);
+        assert_eq!(report[0].severity, Severity::Warning);
+        assert_eq!(report[0].location, at(3, 20));
+        assert_eq!(report[1].severity, Severity::Error);
+        assert_eq!(report[1].location, at(7, 31));
+        assert_eq!(
+            report[1].path.as_deref(),
+            Some("controllers.main.containers.app.livenessProbe.httpGet.port")
+        );
+    }
+
+    #[test]
+    fn test_yaml_output_quotes_yaml11_scalars() {
+        use crate::output::{Format, stream};
+
+        let resources = vec![serde_json::json!({
+            "kind": "ConfigMap",
+            "data": {
+                "enabled": "on",
+                "release": "2024-01-01",
+                "size": "1_000",
+                "version": "1.0.0",
+                "script": "#!/bin/sh\necho hello\n",
+                "padded": "  indented\n",
+            },
+            "items": [{ "name": "a", "ports": [80, 443] }, "yes", []],
+        })];
+
+        let yaml = stream(&resources, Format::Yaml).unwrap();
+        assert_eq!(
+            yaml,
+            r#"---
+data:
+  enabled: "on"
+  padded: "  indented\n"
+  release: "2024-01-01"
+  script: |
+    #!/bin/sh
+    echo hello
+  size: "1_000"
+  version: "1.0.0"
+items:
+- name: a
+  ports:
+  - 80
+  - 443
+- "yes"
+- []
+kind: ConfigMap
+"#
+        );
+        let parsed: Value = serde_yaml_ng::from_str(yaml.trim_start_matches("---\n")).unwrap();
+        assert_eq!(parsed, resources[0]);
+    }
+
+    #[test]
+    fn test_apply_order() {
+        let toml_content = r#"
+[global]
+namespace = "shop"
+
+[namespace]
+create = true
+
+[serviceMonitor.web]
+endpoints = [{ port = "http" }]
+
+[ingress.web]
+hosts = [{ host = "shop.example.com", paths = [{ path = "/", service = { identifier = "web" } }] }]
+
+[controllers.web.containers.app]
+image = "web:latest"
+
+[controllers.api.containers.app]
+image = "api:latest"
+
+[service.web.ports.http]
+port = 80
+
+[persistence.data]
+size = "1Gi"
+
+[secrets.token.stringData]
+token = "secret"
+
+[configMaps.config.data]
+key = "value"
+
+[serviceAccount.web]
+
+[rbac.roles.reader]
+rules = [{ apiGroups = [""], resources = ["pods"], verbs = ["get"] }]
+
+[rawResources.widgets.spec]
+apiVersion = "example.com/v1"
+kind = "Widget"
+metadata = { name = "widget" }
+
+[rawResources.crd.spec]
+apiVersion = "apiextensions.k8s.io/v1"
+kind = "CustomResourceDefinition"
+metadata = { name = "widgets.example.com" }
+"#;
+
+        let values: Values = toml::from_str(toml_content).unwrap();
+        let resources = generate_all_resources(&values).unwrap();
+        let order: Vec<String> = resources
+            .iter()
+            .map(|r| format!("{}/{}", r["kind"].as_str().unwrap(), r["metadata"]["name"]))
+            .collect();
+
+        assert_eq!(
+            order,
+            [
+                "Namespace/\"shop\"",
+                "CustomResourceDefinition/\"widgets.example.com\"",
+                "ServiceAccount/\"web\"",
+                "Role/\"reader\"",
+                "ConfigMap/\"config\"",
+                "Secret/\"token\"",
+                "PersistentVolumeClaim/\"data\"",
+                "Service/\"web\"",
+                "Deployment/\"api\"",
+                "Deployment/\"web\"",
+                "Ingress/\"web\"",
+                "ServiceMonitor/\"web\"",
+                "Widget/\"widget\"",
+            ]
+        );
+    }
 }
@@ -1,56 +1,181 @@
-use bunku::process_values_file;
-use clap::Parser;
+use bunku::diagnostics::{Diagnostic, Severity};
+use bunku::output::{self, Format};
+use bunku::{RenderOptions, Rendered, render, validate};
+use clap::builder::TypedValueParser;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
 use serde_json::Value;
 use std::fs;
 use std::path::Path;
 
 #[derive(Debug, Parser)]
-#[command(version, about, long_about = None)]
-struct Args {
-    #[arg(short, long)]
-    filename: String,
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    // Without a subcommand bunku renders, as it did before subcommands
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    #[command(about = "Render values files into Kubernetes manifests (the default)")]
+    Render(RenderArgs),
+
+    #[command(about = "Check values files and report every problem without rendering")]
+    Validate(ValidateArgs),
+}
+
+#[derive(Debug, clap::Args)]
+struct RenderArgs {
+    #[command(flatten)]
+    values: ValuesArgs,
 
     #[arg(
         short,
         long,
-        help = "Output directory for separate JSON files (optional)"
+        help = "Output directory for one file per resource (optional)"
     )]
     output_dir: Option<String>,
 
-    #[arg(short, long, help = "App name to prepend to resource names")]
+    #[arg(
+        long,
+        default_value_t = Format::JsonList,
+        value_parser = clap::builder::PossibleValuesParser::new(Format::NAMES)
+            .try_map(|format| format.parse::<Format>()),
+        help = "Output format"
+    )]
+    format: Format,
+}
+
+#[derive(Debug, clap::Args)]
+struct ValidateArgs {
+    #[command(flatten)]
+    values: ValuesArgs,
+
+    #[arg(long, value_enum, default_value_t = ReportFormat::Human, help = "Report format")]
+    format: ReportFormat,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    /// One `file:line:column: severity: path: message` line per problem
+    Human,
+    /// A JSON object with a `diagnostics` array, for CI annotations
+    Json,
+}
+
+// Where the values come from, shared by every subcommand
+#[derive(Debug, clap::Args)]
+struct ValuesArgs {
+    #[arg(
+        short,
+        long,
+        required = true,
+        help = "Values file, repeat to layer overrides on top of earlier files"
+    )]
+    filename: Vec<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "Release name to prefix resource names with and use as the instance label"
+    )]
     name: Option<String>,
+
+    #[arg(
+        long,
+        help = "Namespace for every namespaced resource, overriding global.namespace"
+    )]
+    namespace: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "KEY.PATH=VALUE",
+        help = "Override a value, parsed as TOML when possible (repeatable)"
+    )]
+    set: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "KEY.PATH=VALUE",
+        help = "Override a value with a string (repeatable)"
+    )]
+    set_string: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "KEY.PATH=FILE",
+        help = "Override a value with the contents of a file (repeatable)"
+    )]
+    set_file: Vec<String>,
+
+    #[arg(long, help = "Ignore unknown keys in values files instead of failing")]
+    allow_unknown_keys: bool,
+
+    #[arg(
+        long,
+        help = "Report dangling references as warnings instead of failing"
+    )]
+    reference_warnings: bool,
+}
+
+impl ValuesArgs {
+    fn options(&self) -> RenderOptions {
+        RenderOptions {
+            name: self.name.clone(),
+            namespace: self.namespace.clone(),
+            set: self.set.clone(),
+            set_string: self.set_string.clone(),
+            set_file: self.set_file.clone(),
+            allow_unknown_keys: self.allow_unknown_keys,
+            reference_warnings: self.reference_warnings,
+        }
+    }
 }
 
-fn get_resource_filename(resource: &Value) -> String {
+/// `validate --format json` output.
+#[derive(Serialize)]
+struct Report<'a> {
+    valid: bool,
+    errors: usize,
+    warnings: usize,
+    diagnostics: &'a [Diagnostic],
+}
+
+fn get_resource_filename(resource: &Value, format: Format) -> String {
     let kind = resource["kind"].as_str().unwrap_or("unknown");
     let name = resource["metadata"]["name"].as_str().unwrap_or("unnamed");
 
-    format!("{}-{}.json", kind, name)
+    format!("{}-{}.{}", kind, name, format.extension())
 }
 
-fn create_list_object(items: Vec<Value>) -> Value {
-    serde_json::json!({
-        "apiVersion": "v1",
-        "kind": "List",
-        "items": items
-    })
+fn main() {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(Command::Render(args)) => run_render(args),
+        Some(Command::Validate(args)) => run_validate(args),
+        None => run_render(cli.render),
+    }
 }
 
-fn main() {
-    let args = Args::parse();
-
-    match process_values_file(&args.filename) {
-        Ok(mut resources) => {
-            // Apply name prefix if provided
-            if let Some(app_name) = &args.name {
-                for resource in &mut resources {
-                    if let Some(metadata) = resource.get_mut("metadata") {
-                        if let Some(name) = metadata.get("name").and_then(|n| n.as_str()) {
-                            let new_name = format!("{}-{}", app_name, name);
-                            metadata["name"] = serde_json::Value::String(new_name);
-                        }
-                    }
-                }
+fn run_render(args: RenderArgs) {
+    match render(&args.values.filename, &args.values.options()) {
+        Ok(Rendered {
+            resources,
+            warnings,
+        }) => {
+            for warning in &warnings {
+                eprintln!("Warning: {}", warning);
             }
 
             match args.output_dir {
@@ -63,11 +188,11 @@ fn main() {
 
                     // Write each resource to a separate file
                     for resource in &resources {
-                        let filename = get_resource_filename(resource);
+                        let filename = get_resource_filename(resource, args.format);
                         let filepath = Path::new(&output_dir).join(&filename);
-                        let json = serde_json::to_string_pretty(resource).unwrap();
+                        let content = output::document(resource, args.format).unwrap();
 
-                        if let Err(e) = fs::write(&filepath, json) {
+                        if let Err(e) = fs::write(&filepath, content) {
                             eprintln!("Error writing file {}: {}", filepath.display(), e);
                             std::process::exit(1);
                         }
@@ -75,12 +200,7 @@ fn main() {
                         println!("Wrote {}", filepath.display());
                     }
                 }
-                None => {
-                    // Wrap resources in a List object for stdout
-                    let list = create_list_object(resources);
-                    let json = serde_json::to_string_pretty(&list).unwrap();
-                    println!("{}", json);
-                }
+                None => print!("{}", output::stream(&resources, args.format).unwrap()),
             }
         }
         Err(e) => {
@@ -89,3 +209,48 @@ fn main() {
         }
     }
 }
+
+fn run_validate(args: ValidateArgs) {
+    let diagnostics = validate(&args.values.filename, &args.values.options());
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+
+    match args.format {
+        ReportFormat::Human => {
+            for diagnostic in &diagnostics {
+                println!("{}", diagnostic);
+            }
+            println!(
+                "{} {}, {} {}",
+                errors,
+                plural(errors, "error"),
+                warnings,
+                plural(warnings, "warning")
+            );
+        }
+        ReportFormat::Json => {
+            let report = Report {
+                valid: errors == 0,
+                errors,
+                warnings,
+                diagnostics: &diagnostics,
+            };
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        }
+    }
+
+    if errors > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn plural(count: usize, word: &str) -> String {
+    if count == 1 {
+        word.to_string()
+    } else {
+        format!("{}s", word)
+    }
+}
@@ -0,0 +1,237 @@
+use crate::error::BunkuError;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// How rendered resources are written, to stdout or one file per resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// A `---`-separated multi-document YAML stream.
+    Yaml,
+    /// Pretty-printed JSON objects one after another.
+    Json,
+    /// A single `kind: List` object holding every resource.
+    #[default]
+    JsonList,
+    /// One compact JSON object per line.
+    Ndjson,
+}
+
+impl Format {
+    pub const NAMES: [&'static str; 4] = ["yaml", "json", "json-list", "ndjson"];
+
+    /// Extension of the files written with `--output-dir`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Yaml => "yaml",
+            Format::Json | Format::JsonList | Format::Ndjson => "json",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "yaml" => Ok(Format::Yaml),
+            "json" => Ok(Format::Json),
+            "json-list" => Ok(Format::JsonList),
+            "ndjson" => Ok(Format::Ndjson),
+            other => Err(format!(
+                "unknown format {:?}, expected one of {}",
+                other,
+                Format::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Yaml => "yaml",
+            Format::Json => "json",
+            Format::JsonList => "json-list",
+            Format::Ndjson => "ndjson",
+        })
+    }
+}
+
+/// Write every resource as one stream, ending with a newline.
+pub fn stream(resources: &[Value], format: Format) -> Result<String, BunkuError> {
+    let mut out = String::new();
+    match format {
+        Format::JsonList => {
+            let list = serde_json::json!({
+                "apiVersion": "v1",
+                "kind": "List",
+                "items": resources,
+            });
+            out.push_str(&serde_json::to_string_pretty(&list)?);
+            out.push('\n');
+        }
+        Format::Yaml | Format::Json | Format::Ndjson => {
+            for resource in resources {
+                if format == Format::Yaml {
+                    out.push_str("---\n");
+                }
+                out.push_str(&document(resource, format)?);
+                if !out.ends_with('\n') {
+                    out.push('\n');
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Write a single resource, as stored in its own file.
+pub fn document(resource: &Value, format: Format) -> Result<String, BunkuError> {
+    Ok(match format {
+        Format::Yaml => {
+            let mut out = String::new();
+            yaml_node(&mut out, resource, 0, false)?;
+            out
+        }
+        Format::Json | Format::JsonList => serde_json::to_string_pretty(resource)?,
+        Format::Ndjson => serde_json::to_string(resource)?,
+    })
+}
+
+/// Write a value as block YAML with `indent` as its column. Maps and lists
+/// start on a new line unless `inline`, which continues a `- ` entry.
+fn yaml_node(
+    out: &mut String,
+    value: &Value,
+    indent: usize,
+    inline: bool,
+) -> Result<(), BunkuError> {
+    match value {
+        Value::Object(map) if !map.is_empty() => yaml_map(out, map, indent, inline),
+        Value::Array(items) if !items.is_empty() => {
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 || !inline {
+                    out.push_str(&" ".repeat(indent));
+                }
+                out.push_str("- ");
+                yaml_node(out, item, indent + 2, true)?;
+            }
+            Ok(())
+        }
+        scalar => {
+            out.push_str(&yaml_scalar(scalar, indent)?);
+            out.push('\n');
+            Ok(())
+        }
+    }
+}
+
+fn yaml_map(
+    out: &mut String,
+    map: &Map<String, Value>,
+    indent: usize,
+    inline: bool,
+) -> Result<(), BunkuError> {
+    for (index, (key, value)) in map.iter().enumerate() {
+        if index > 0 || !inline {
+            out.push_str(&" ".repeat(indent));
+        }
+        out.push_str(&yaml_key(key)?);
+        out.push(':');
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                out.push('\n');
+                yaml_map(out, map, indent + 2, false)?;
+            }
+            // Lists sit at the same column as their key, as kubectl writes them
+            Value::Array(items) if !items.is_empty() => {
+                out.push('\n');
+                yaml_node(out, value, indent, false)?;
+            }
+            scalar => {
+                out.push(' ');
+                yaml_node(out, scalar, indent + 2, true)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn yaml_key(key: &str) -> Result<String, BunkuError> {
+    let rendered = yaml_scalar(&Value::String(key.to_string()), 0)?;
+    if rendered.contains('\n') {
+        return Ok(serde_json::to_string(key)?);
+    }
+    Ok(rendered)
+}
+
+/// Render a scalar, continuing multi-line block strings at `indent`.
+///
+/// kubectl reads YAML 1.1, where `yes`, `on` or `2024-01-01` are not strings,
+/// so those are quoted even though YAML 1.2 emitters leave them plain.
+fn yaml_scalar(value: &Value, indent: usize) -> Result<String, BunkuError> {
+    let Value::String(s) = value else {
+        return Ok(match value {
+            Value::Object(_) => "{}".to_string(),
+            Value::Array(_) => "[]".to_string(),
+            scalar => scalar.to_string(),
+        });
+    };
+    if is_yaml11_special(s) {
+        return Ok(serde_json::to_string(s)?);
+    }
+
+    let rendered = serde_yaml_ng::to_string(s)?;
+    let rendered = rendered.strip_suffix('\n').unwrap_or(&rendered);
+    let Some((header, body)) = rendered.split_once('\n') else {
+        return Ok(rendered.to_string());
+    };
+    // An explicit indentation indicator is relative to the parent node, which
+    // the emitter did not know, so such strings are double-quoted instead
+    if header.contains(|c: char| c.is_ascii_digit()) {
+        return Ok(serde_json::to_string(s)?);
+    }
+
+    // The body is indented for a top-level string, one step past column 0
+    let parent = " ".repeat(indent.saturating_sub(2));
+    let mut block = header.to_string();
+    for line in body.split('\n') {
+        block.push('\n');
+        if !line.is_empty() {
+            block.push_str(&parent);
+            block.push_str(line);
+        }
+    }
+    Ok(block)
+}
+
+fn is_yaml11_special(s: &str) -> bool {
+    let mut chars = s.chars();
+    let starts_numeric = match (chars.next(), chars.next()) {
+        (Some(first), _) if first.is_ascii_digit() => true,
+        (Some('+' | '-' | '.'), Some(second)) => second.is_ascii_digit(),
+        _ => false,
+    };
+    starts_numeric
+        || matches!(
+            s,
+            "y" | "Y"
+                | "yes"
+                | "Yes"
+                | "YES"
+                | "n"
+                | "N"
+                | "no"
+                | "No"
+                | "NO"
+                | "on"
+                | "On"
+                | "ON"
+                | "off"
+                | "Off"
+                | "OFF"
+                | "<<"
+                | "="
+        )
+}
@@ -0,0 +1,266 @@
+use crate::error::BunkuError;
+use std::fs;
+use toml::{Table, Value};
+
+/// A path segment: a table key, optionally followed by array indices as in
+/// `rules[0]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Apply `--set`, `--set-string` and `--set-file` overrides, in that order.
+pub fn apply_overrides(
+    table: &mut Table,
+    set: &[String],
+    set_string: &[String],
+    set_file: &[String],
+) -> Result<(), BunkuError> {
+    for assignment in set {
+        let (path, raw) = split_assignment("--set", assignment)?;
+        set_path(table, "--set", path, parse_value(raw))?;
+    }
+    for assignment in set_string {
+        let (path, raw) = split_assignment("--set-string", assignment)?;
+        set_path(table, "--set-string", path, Value::String(raw.to_string()))?;
+    }
+    for assignment in set_file {
+        let (path, file) = split_assignment("--set-file", assignment)?;
+        let content = fs::read_to_string(file).map_err(|e| {
+            BunkuError::Validation(format!("--set-file {}: cannot read {}: {}", path, file, e))
+        })?;
+        set_path(table, "--set-file", path, Value::String(content))?;
+    }
+    Ok(())
+}
+
+fn split_assignment<'a>(flag: &str, assignment: &'a str) -> Result<(&'a str, &'a str), BunkuError> {
+    // Split on the first `=` outside quotes so quoted keys may contain one
+    let mut quote = None;
+    for (index, c) in assignment.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (None, '=') => return Ok((&assignment[..index], &assignment[index + 1..])),
+            _ => {}
+        }
+    }
+    Err(BunkuError::Validation(format!(
+        "{} {}: expected key.path=value",
+        flag, assignment
+    )))
+}
+
+/// Values that parse as TOML (numbers, booleans, arrays, inline tables) keep
+/// their type; anything else is a string.
+fn parse_value(raw: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+/// Split a dotted path such as `global.labels."app.kubernetes.io/part-of"` or
+/// `route.main.rules[0].timeouts.request`.
+pub(crate) fn parse_path(flag: &str, path: &str) -> Result<Vec<Segment>, BunkuError> {
+    let invalid = |reason: &str| BunkuError::Validation(format!("{} {}: {}", flag, path, reason));
+
+    let mut segments = Vec::new();
+    let mut chars = path.chars().peekable();
+    loop {
+        let key = match chars.peek() {
+            Some(&quote @ ('"' | '\'')) => {
+                chars.next();
+                let mut key = String::new();
+                loop {
+                    match chars.next() {
+                        Some(c) if c == quote => break,
+                        Some(c) => key.push(c),
+                        None => return Err(invalid("unterminated quoted key")),
+                    }
+                }
+                key
+            }
+            _ => {
+                let mut key = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == '.' || c == '[' {
+                        break;
+                    }
+                    key.push(c);
+                    chars.next();
+                }
+                if key.is_empty() {
+                    return Err(invalid("empty key"));
+                }
+                key
+            }
+        };
+        segments.push(Segment::Key(key));
+
+        while chars.peek() == Some(&'[') {
+            chars.next();
+            let digits: String = chars.by_ref().take_while(|&c| c != ']').collect();
+            let index = digits
+                .parse()
+                .map_err(|_| invalid(&format!("invalid index [{}]", digits)))?;
+            segments.push(Segment::Index(index));
+        }
+
+        match chars.next() {
+            None => return Ok(segments),
+            Some('.') => {}
+            Some(c) => return Err(invalid(&format!("unexpected {:?}", c))),
+        }
+    }
+}
+
+fn set_path(table: &mut Table, flag: &str, path: &str, value: Value) -> Result<(), BunkuError> {
+    let segments = parse_path(flag, path)?;
+    let (last, parents) = segments.split_last().expect("paths have a segment");
+
+    let mut root = Value::Table(std::mem::take(table));
+    let result = check_path(&root, &segments)
+        .and_then(|()| assign(&mut root, parents, last, value))
+        .map_err(|depth| {
+            let parent = &segments[..depth];
+            let mut found = describe(lookup(&root, parent));
+            // A missing key would be created as a table, which cannot be indexed
+            let expected = match segments[depth] {
+                Segment::Key(_) if matches!(lookup(&root, parent), Some(Value::Table(_))) => {
+                    found = format!(
+                        "{} without {}",
+                        found,
+                        render_path(&segments[depth..=depth])
+                    );
+                    "an array containing the index"
+                }
+                Segment::Key(_) => "a table",
+                Segment::Index(_) => "an array containing the index",
+            };
+            BunkuError::Validation(format!(
+                "{} {}: nearest valid parent {} is {}, expected {}",
+                flag,
+                path,
+                render_path(parent),
+                found,
+                expected
+            ))
+        });
+    if let Value::Table(restored) = root {
+        *table = restored;
+    }
+    result
+}
+
+/// Check that the path can be assigned before anything is inserted, so a
+/// failed override names the parent that really exists. Missing keys may only
+/// be followed by more keys, since they are created as tables. On failure
+/// returns how many segments led to the nearest valid parent.
+fn check_path(root: &Value, segments: &[Segment]) -> Result<(), usize> {
+    let mut current = Some(root);
+    let mut depth = 0;
+    for (position, segment) in segments.iter().enumerate() {
+        let last = position + 1 == segments.len();
+        current = match (segment, current) {
+            (Segment::Key(_), None) => None,
+            (Segment::Key(key), Some(Value::Table(table))) => table.get(key),
+            (Segment::Index(index), Some(Value::Array(items)))
+                if *index < items.len() || (last && *index == items.len()) =>
+            {
+                items.get(*index)
+            }
+            _ => return Err(depth),
+        };
+        if current.is_some() {
+            depth = position + 1;
+        }
+    }
+    Ok(())
+}
+
+/// Walk to the parent and assign, creating missing tables on the way. On
+/// failure returns how many segments led to the nearest valid parent.
+fn assign(
+    root: &mut Value,
+    parents: &[Segment],
+    last: &Segment,
+    value: Value,
+) -> Result<(), usize> {
+    let mut current = root;
+    for (depth, segment) in parents.iter().enumerate() {
+        current = match (segment, current) {
+            (Segment::Key(key), Value::Table(table)) => table
+                .entry(key.clone())
+                .or_insert_with(|| Value::Table(Table::new())),
+            (Segment::Index(index), Value::Array(items)) if *index < items.len() => {
+                &mut items[*index]
+            }
+            _ => return Err(depth),
+        };
+    }
+
+    match (last, current) {
+        (Segment::Key(key), Value::Table(table)) => {
+            table.insert(key.clone(), value);
+            Ok(())
+        }
+        (Segment::Index(index), Value::Array(items)) if *index <= items.len() => {
+            if *index == items.len() {
+                items.push(value);
+            } else {
+                items[*index] = value;
+            }
+            Ok(())
+        }
+        _ => Err(parents.len()),
+    }
+}
+
+fn lookup<'a>(root: &'a Value, segments: &[Segment]) -> Option<&'a Value> {
+    segments
+        .iter()
+        .try_fold(root, |current, segment| match (segment, current) {
+            (Segment::Key(key), Value::Table(table)) => table.get(key),
+            (Segment::Index(index), Value::Array(items)) => items.get(*index),
+            _ => None,
+        })
+}
+
+fn describe(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::Array(items)) => format!("an array with {} entries", items.len()),
+        Some(Value::Table(_)) => "a table".to_string(),
+        Some(value) => match value.type_str() {
+            type_name @ ("integer" | "array") => format!("an {}", type_name),
+            type_name => format!("a {}", type_name),
+        },
+        None => "missing".to_string(),
+    }
+}
+
+/// Render a path the way `--set` accepts it, quoting keys where needed.
+pub fn render_path(segments: &[Segment]) -> String {
+    if segments.is_empty() {
+        return "the root".to_string();
+    }
+
+    let mut rendered = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Key(key) => {
+                if !rendered.is_empty() {
+                    rendered.push('.');
+                }
+                if key.contains(['.', '[', ']', '"']) {
+                    rendered.push_str(&format!("'{}'", key));
+                } else {
+                    rendered.push_str(key);
+                }
+            }
+            Segment::Index(index) => rendered.push_str(&format!("[{}]", index)),
+        }
+    }
+    rendered
+}
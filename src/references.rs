@@ -0,0 +1,392 @@
+use crate::error::BunkuError;
+use crate::values::{Container, Controller, Service, Values};
+use indexmap::IndexMap;
+use serde_json::Value;
+
+/// Check that every reference between values entries points at something that
+/// exists and is enabled, before any generator runs. Each problem names the
+/// TOML path of the reference.
+pub fn check(values: &Values) -> Vec<BunkuError> {
+    let mut checker = Checker {
+        values,
+        problems: Vec::new(),
+    };
+    checker.controllers();
+    checker.services();
+    checker.routes();
+    checker.ingresses();
+    checker.rbac();
+    checker.service_monitors();
+    checker.network_policies();
+    checker.persistence();
+    checker.problems
+}
+
+/// A reference to an entry that does not exist. Generators that cannot
+/// render such a reference report it the same way, so it is listed once.
+pub(crate) fn undefined(path: impl Into<String>, kind: &str, key: &str) -> BunkuError {
+    BunkuError::invalid(path, format!("{} {:?} is not defined", kind, key))
+}
+
+/// A reference to an entry that exists but is disabled.
+pub(crate) fn disabled(path: impl Into<String>, kind: &str, key: &str) -> BunkuError {
+    BunkuError::invalid(path, format!("{} {:?} is disabled", kind, key))
+}
+
+/// A Service backend without a port, on a Service with none to default to.
+pub(crate) fn no_ports(path: impl Into<String>, key: &str) -> BunkuError {
+    BunkuError::invalid(path, format!("service {:?} has no ports", key))
+}
+
+struct Checker<'a> {
+    values: &'a Values,
+    problems: Vec<BunkuError>,
+}
+
+impl<'a> Checker<'a> {
+    fn problem(&mut self, path: impl Into<String>, message: impl ToString) {
+        self.problems.push(BunkuError::invalid(path, message));
+    }
+
+    /// Look up `key` in `entries`, reporting it when missing or disabled.
+    fn target<T>(
+        &mut self,
+        path: &str,
+        kind: &str,
+        entries: &'a IndexMap<String, T>,
+        key: &str,
+        enabled: impl Fn(&T) -> bool,
+    ) -> Option<&'a T> {
+        match entries.get(key) {
+            None => {
+                self.problems.push(undefined(path, kind, key));
+                None
+            }
+            Some(entry) if !enabled(entry) => {
+                self.problems.push(disabled(path, kind, key));
+                None
+            }
+            Some(entry) => Some(entry),
+        }
+    }
+
+    fn controller(&mut self, path: &str, key: &str) {
+        self.target(path, "controller", &self.values.controllers, key, |c| {
+            c.enabled
+        });
+    }
+
+    fn service(&mut self, path: &str, key: &str) -> Option<&'a Service> {
+        self.target(path, "service", &self.values.service, key, |s| s.enabled)
+    }
+
+    fn service_account(&mut self, path: &str, key: &str) {
+        self.target(
+            path,
+            "serviceAccount",
+            &self.values.service_account,
+            key,
+            |sa| sa.enabled,
+        );
+    }
+
+    /// A Service backend: the Service must exist and expose the port number,
+    /// or have a port to default to when none is given.
+    fn service_port(&mut self, path: &str, key: &str, port: Option<i32>) {
+        let Some(service) = self.service(&format!("{}.identifier", path), key) else {
+            return;
+        };
+        match port {
+            Some(port) if !service.ports.values().any(|p| p.port == port) => {
+                self.problem(
+                    format!("{}.port", path),
+                    format!("service {:?} has no port {}", key, port),
+                );
+            }
+            None if service.ports.is_empty() => {
+                self.problems.push(no_ports(format!("{}.port", path), key));
+            }
+            _ => {}
+        }
+    }
+
+    fn controllers(&mut self) {
+        for (name, controller) in &self.values.controllers {
+            if !controller.enabled {
+                continue;
+            }
+            let path = format!("controllers.{}", name);
+
+            if let Some(identifier) = controller
+                .service_account
+                .as_ref()
+                .and_then(|sa| sa.identifier.as_ref())
+            {
+                self.service_account(&format!("{}.serviceAccount.identifier", path), identifier);
+            }
+
+            for (field, containers) in [
+                ("containers", &controller.containers),
+                ("initContainers", &controller.init_containers),
+            ] {
+                for (container_name, container) in containers {
+                    self.volume_mounts(
+                        &format!("{}.{}.{}", path, field, container_name),
+                        controller,
+                        container,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Mounts name a persistence entry or, in a StatefulSet, one of its own
+    /// claim templates.
+    fn volume_mounts(&mut self, path: &str, controller: &Controller, container: &Container) {
+        let claim_templates: Vec<&str> = match &controller.statefulset {
+            Some(statefulset) if controller.r#type == "statefulset" => statefulset
+                .volume_claim_templates
+                .iter()
+                .filter(|item| item.enabled)
+                .filter_map(|item| item.name.as_deref())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        for (index, mount) in container.volume_mounts.iter().enumerate() {
+            if claim_templates.contains(&mount.name.as_str()) {
+                continue;
+            }
+            self.target(
+                &format!("{}.volumeMounts[{}].name", path, index),
+                "persistence",
+                &self.values.persistence,
+                &mount.name,
+                |item| item.enabled,
+            );
+        }
+    }
+
+    fn services(&mut self) {
+        for (name, service) in &self.values.service {
+            // Without `controller` the selector is inferred from the key
+            if service.enabled
+                && let Some(controller) = &service.controller
+            {
+                self.controller(&format!("service.{}.controller", name), controller);
+            }
+        }
+    }
+
+    fn routes(&mut self) {
+        for (name, route) in &self.values.route {
+            if !route.enabled {
+                continue;
+            }
+
+            for (rule_index, rule) in route.rules.iter().enumerate() {
+                let rule_path = format!("route.{}.rules[{}]", name, rule_index);
+
+                for (index, backend) in rule.backend_refs.iter().enumerate() {
+                    if let Some(identifier) = &backend.identifier {
+                        self.service_port(
+                            &format!("{}.backendRefs[{}]", rule_path, index),
+                            identifier,
+                            backend.port,
+                        );
+                    }
+                }
+
+                for (index, filter) in rule.filters.iter().flatten().enumerate() {
+                    let backend = filter.pointer("/requestMirror/backendRef");
+                    if let Some(identifier) = backend
+                        .and_then(|b| b.get("identifier"))
+                        .and_then(Value::as_str)
+                    {
+                        let port = backend
+                            .and_then(|b| b.get("port"))
+                            .and_then(Value::as_i64)
+                            .map(|p| p as i32);
+                        self.service_port(
+                            &format!("{}.filters[{}].requestMirror.backendRef", rule_path, index),
+                            identifier,
+                            port,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn ingresses(&mut self) {
+        for (name, ingress) in &self.values.ingress {
+            if !ingress.enabled {
+                continue;
+            }
+
+            for (host_index, host) in ingress.hosts.iter().enumerate() {
+                for (path_index, path) in host.paths.iter().enumerate() {
+                    if let Some(identifier) = &path.service.identifier {
+                        self.service_port(
+                            &format!(
+                                "ingress.{}.hosts[{}].paths[{}].service",
+                                name, host_index, path_index
+                            ),
+                            identifier,
+                            path.service.port,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn rbac(&mut self) {
+        for (name, binding) in &self.values.rbac.bindings {
+            if !binding.enabled {
+                continue;
+            }
+            let path = format!("rbac.bindings.{}", name);
+
+            // Only bunku roles can be checked, any other name is used verbatim
+            let role_ref = &binding.role_ref;
+            if let Some(role) = self.values.rbac.roles.get(&role_ref.name) {
+                let expected = if role.cluster_wide {
+                    "ClusterRole"
+                } else {
+                    "Role"
+                };
+                if !role.enabled {
+                    self.problem(
+                        format!("{}.roleRef.name", path),
+                        format!("role {:?} is disabled", role_ref.name),
+                    );
+                } else if role_ref.kind != expected {
+                    self.problem(
+                        format!("{}.roleRef.kind", path),
+                        format!(
+                            "role {:?} is a {}, not a {}",
+                            role_ref.name, expected, role_ref.kind
+                        ),
+                    );
+                }
+            }
+
+            for (index, subject) in binding.subjects.iter().enumerate() {
+                if let Some(identifier) = &subject.identifier {
+                    self.service_account(
+                        &format!("{}.subjects[{}].identifier", path, index),
+                        identifier,
+                    );
+                }
+            }
+        }
+    }
+
+    fn service_monitors(&mut self) {
+        for (name, monitor) in &self.values.service_monitor {
+            if !monitor.enabled {
+                continue;
+            }
+            let path = format!("serviceMonitor.{}", name);
+
+            // Reported at `service` even when it defaults to the monitor's key
+            let service_key = monitor.service.as_deref().unwrap_or(name);
+            let Some(service) = self.service(&format!("{}.service", path), service_key) else {
+                continue;
+            };
+
+            for (index, endpoint) in monitor.endpoints.iter().enumerate() {
+                if !service.ports.contains_key(&endpoint.port) {
+                    self.problem(
+                        format!("{}.endpoints[{}].port", path, index),
+                        format!(
+                            "service {:?} has no port named {:?}",
+                            service_key, endpoint.port
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn network_policies(&mut self) {
+        for (name, policy) in &self.values.networkpolicies {
+            if !policy.enabled {
+                continue;
+            }
+            let path = format!("networkpolicies.{}", name);
+
+            if let Some(controller) = &policy.controller {
+                self.controller(&format!("{}.controller", path), controller);
+            }
+
+            let ingress = policy
+                .ingress
+                .iter()
+                .enumerate()
+                .map(|(index, rule)| (format!("{}.ingress[{}].from", path, index), &rule.from));
+            let egress = policy
+                .egress
+                .iter()
+                .enumerate()
+                .map(|(index, rule)| (format!("{}.egress[{}].to", path, index), &rule.to));
+            for (peers_path, peers) in ingress.chain(egress) {
+                for (index, peer) in peers.iter().enumerate() {
+                    if let Some(controller) = peer.get("controller").and_then(Value::as_str) {
+                        self.controller(
+                            &format!("{}[{}].controller", peers_path, index),
+                            controller,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn persistence(&mut self) {
+        for (name, item) in &self.values.persistence {
+            if !item.enabled {
+                continue;
+            }
+            let path = format!("persistence.{}", name);
+
+            if let Some(identifier) = &item.identifier {
+                let identifier_path = format!("{}.identifier", path);
+                match item.r#type.as_str() {
+                    "configMap" => {
+                        self.target(
+                            &identifier_path,
+                            "configMap",
+                            &self.values.config_maps,
+                            identifier,
+                            |cm| cm.enabled,
+                        );
+                    }
+                    "secret" => {
+                        self.target(
+                            &identifier_path,
+                            "secret",
+                            &self.values.secrets,
+                            identifier,
+                            |secret| secret.enabled,
+                        );
+                    }
+                    _ => {}
+                }
+            }
+
+            for (mount_index, mount) in item.global_mounts.iter().enumerate() {
+                for (index, controller) in mount.controllers.iter().enumerate() {
+                    self.controller(
+                        &format!(
+                            "{}.globalMounts[{}].controllers[{}]",
+                            path, mount_index, index
+                        ),
+                        controller,
+                    );
+                }
+            }
+        }
+    }
+}
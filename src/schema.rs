@@ -0,0 +1,312 @@
+use crate::diagnostics::Location;
+use crate::error::BunkuError;
+use crate::layers::SourceFile;
+use crate::overrides::{Segment, parse_path, render_path};
+use crate::values::Values;
+use serde::Deserialize;
+use serde::de::value::{Error as ProbeError, StrDeserializer};
+use serde::de::{
+    self, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+use std::cell::Cell;
+use std::fmt;
+use std::ops::Range;
+use toml::Table;
+
+/// A key no `Values` struct knows, where it was written and what was likely
+/// meant instead.
+#[derive(Debug, Clone)]
+pub struct UnknownKey {
+    pub path: String,
+    /// `None` when the key came from a command line override.
+    pub location: Option<Location>,
+    pub suggestion: Option<String>,
+}
+
+impl UnknownKey {
+    /// The problem without the key's path and location.
+    pub fn message(&self) -> String {
+        match &self.suggestion {
+            Some(suggestion) => format!("unknown key (did you mean `{}`?)", suggestion),
+            None => "unknown key".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}: ", location)?,
+            None => f.write_str("command line: ")?,
+        }
+        write!(f, "unknown key `{}`", self.path)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean `{}`?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Deserialise the merged values. In strict mode every key that no `Values`
+/// struct knows is reported with its location and a suggestion.
+pub fn deserialize_values(
+    table: Table,
+    sources: &[SourceFile],
+    strict: bool,
+) -> Result<Values, BunkuError> {
+    let value = toml::Value::Table(table);
+    // serde stops at the first missing field, so a misspelt required key
+    // would hide every unknown key after it; walk the whole table first
+    let mut unknown = Vec::new();
+    if strict {
+        find_unknown_keys(&value, &mut Vec::new(), &mut unknown);
+    }
+    let result = serde_ignored::deserialize(value, |path| {
+        let path = segments(&path);
+        if !unknown.contains(&path) {
+            unknown.push(path);
+        }
+    });
+
+    if strict && !unknown.is_empty() {
+        return Err(BunkuError::UnknownKeys(
+            unknown
+                .iter()
+                .map(|path| describe_unknown_key(path, sources))
+                .collect(),
+        ));
+    }
+
+    Ok(result?)
+}
+
+/// Collect the keys of every table that maps to a struct but names none of
+/// its fields. Tables that are not structs, like maps keyed by identifier,
+/// are only descended into.
+fn find_unknown_keys(
+    value: &toml::Value,
+    path: &mut Vec<Segment>,
+    unknown: &mut Vec<Vec<Segment>>,
+) {
+    match value {
+        toml::Value::Table(table) => {
+            let fields = expected_fields(path);
+            for (key, value) in table {
+                path.push(Segment::Key(key.clone()));
+                if fields.is_some_and(|fields| !fields.contains(&key.as_str())) {
+                    unknown.push(path.clone());
+                } else {
+                    find_unknown_keys(value, path, unknown);
+                }
+                path.pop();
+            }
+        }
+        toml::Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                path.push(Segment::Index(index));
+                find_unknown_keys(item, path, unknown);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn segments(path: &serde_ignored::Path) -> Vec<Segment> {
+    let mut segments = match path {
+        serde_ignored::Path::Root => return Vec::new(),
+        serde_ignored::Path::Seq { parent, .. }
+        | serde_ignored::Path::Map { parent, .. }
+        | serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => segments(parent),
+    };
+    match path {
+        serde_ignored::Path::Seq { index, .. } => segments.push(Segment::Index(*index)),
+        serde_ignored::Path::Map { key, .. } => segments.push(Segment::Key(key.clone())),
+        _ => {}
+    }
+    segments
+}
+
+fn describe_unknown_key(path: &[Segment], sources: &[SourceFile]) -> UnknownKey {
+    let suggestion = match path.split_last() {
+        Some((Segment::Key(key), parent)) => {
+            suggest(key, expected_fields(parent).unwrap_or_default())
+        }
+        _ => None,
+    };
+    UnknownKey {
+        path: render_path(path),
+        location: locate(path, sources),
+        suggestion,
+    }
+}
+
+/// Find the last file that sets the value at a TOML path such as
+/// `controllers.main.containers.app.livenessProbe.httpGet.port`. Values that
+/// cannot be pointed at, like array entries, fall back to their nearest key.
+pub fn locate_path(path: &str, sources: &[SourceFile]) -> Option<Location> {
+    let segments = parse_path("path", path).ok()?;
+    (1..=segments.len())
+        .rev()
+        .find_map(|len| locate(&segments[..len], sources))
+}
+
+/// Find the last file that sets the key.
+fn locate(path: &[Segment], sources: &[SourceFile]) -> Option<Location> {
+    sources.iter().rev().find_map(|source| {
+        let document = toml_edit::ImDocument::parse(source.content.as_str()).ok()?;
+        let span = key_span(document.as_item(), path)?;
+        Some(Location::at(&source.path, &source.content, span.start))
+    })
+}
+
+fn key_span(root: &toml_edit::Item, path: &[Segment]) -> Option<Range<usize>> {
+    let (Segment::Key(last), parents) = path.split_last()? else {
+        return None;
+    };
+
+    let mut item = root;
+    for segment in parents {
+        item = match segment {
+            Segment::Key(key) => item.get(key.as_str())?,
+            Segment::Index(index) => item.get(*index)?,
+        };
+    }
+
+    match item {
+        toml_edit::Item::Table(table) => table.key(last)?.span(),
+        toml_edit::Item::Value(toml_edit::Value::InlineTable(table)) => table.key(last)?.span(),
+        _ => None,
+    }
+}
+
+fn suggest(key: &str, candidates: &[&str]) -> Option<String> {
+    candidates
+        .iter()
+        .map(|candidate| {
+            let score = strsim::jaro_winkler(&key.to_lowercase(), &candidate.to_lowercase());
+            (score, candidate)
+        })
+        .filter(|(score, _)| *score > 0.8)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+/// Field names of the struct found at `path` inside `Values`, discovered by
+/// feeding the derived `Deserialize` impls a probe that follows the path.
+/// `None` when the path does not lead to a struct.
+fn expected_fields(path: &[Segment]) -> Option<&'static [&'static str]> {
+    let fields = Cell::new(None);
+    let _ = Values::deserialize(FieldProbe {
+        path,
+        fields: &fields,
+    });
+    fields.get()
+}
+
+struct FieldProbe<'a> {
+    path: &'a [Segment],
+    fields: &'a Cell<Option<&'static [&'static str]>>,
+}
+
+impl<'de> Deserializer<'de> for FieldProbe<'_> {
+    type Error = ProbeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.path.first() {
+            Some(Segment::Key(_)) => visitor.visit_map(ProbeMap { probe: Some(self) }),
+            Some(Segment::Index(_)) => visitor.visit_seq(ProbeSeq { probe: Some(self) }),
+            None => Err(de::Error::custom("end of path")),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if self.path.is_empty() {
+            self.fields.set(Some(fields));
+            return Err(de::Error::custom("found struct"));
+        }
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map enum
+        identifier ignored_any
+    }
+}
+
+/// A map with the single key the path continues through.
+struct ProbeMap<'a> {
+    probe: Option<FieldProbe<'a>>,
+}
+
+impl<'de> MapAccess<'de> for ProbeMap<'_> {
+    type Error = ProbeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.probe.as_ref().and_then(|probe| probe.path.first()) {
+            Some(Segment::Key(key)) => {
+                let key: StrDeserializer<ProbeError> = key.as_str().into_deserializer();
+                seed.deserialize(key).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let probe = self.probe.take().expect("value follows its key");
+        seed.deserialize(FieldProbe {
+            path: &probe.path[1..],
+            fields: probe.fields,
+        })
+    }
+}
+
+/// A sequence with a single element, the one the path continues through.
+struct ProbeSeq<'a> {
+    probe: Option<FieldProbe<'a>>,
+}
+
+impl<'de> SeqAccess<'de> for ProbeSeq<'_> {
+    type Error = ProbeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        match self.probe.take() {
+            Some(probe) => seed
+                .deserialize(FieldProbe {
+                    path: &probe.path[1..],
+                    fields: probe.fields,
+                })
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
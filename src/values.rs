@@ -1,6 +1,6 @@
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -11,6 +11,10 @@ pub struct Values {
     #[serde(default = "default_pod_options_strategy")]
     pub default_pod_options_strategy: String,
 
+    /// How the `merge` strategy combines lists: `append` or `replace`.
+    #[serde(default = "default_pod_options_list_strategy")]
+    pub default_pod_options_list_strategy: String,
+
     #[serde(default)]
     pub default_pod_options: PodOptions,
 
@@ -49,61 +53,57 @@ pub struct Values {
 
     #[serde(default)]
     pub raw_resources: IndexMap<String, RawResource>,
+
+    #[serde(default)]
+    pub namespace: NamespaceConfig,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct GlobalConfig {
+    /// Release name prefixed to every generated name, also set by `--name`.
+    pub release_name: Option<String>,
+    /// Namespace stamped on every namespaced resource, also set by `--namespace`.
+    pub namespace: Option<String>,
     #[serde(default)]
     pub propagate_global_metadata_to_pods: bool,
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
     #[serde(default)]
-    pub annotations: HashMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
 }
 
+/// Pod-level settings. Every field is optional so that merging can tell an
+/// unset field from one explicitly set to its zero value.
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct PodOptions {
-    #[serde(default)]
     pub affinity: Option<serde_json::Value>,
-    #[serde(default)]
-    pub annotations: HashMap<String, String>,
-    #[serde(default)]
-    pub automount_service_account_token: bool,
-    #[serde(default)]
+    pub annotations: Option<BTreeMap<String, String>>,
+    pub automount_service_account_token: Option<bool>,
     pub dns_config: Option<serde_json::Value>,
     pub dns_policy: Option<String>,
-    #[serde(default)]
-    pub enable_service_links: bool,
+    pub enable_service_links: Option<bool>,
     pub hostname: Option<String>,
-    #[serde(default)]
-    pub host_aliases: Vec<serde_json::Value>,
-    #[serde(default)]
-    pub host_ipc: bool,
-    #[serde(default)]
-    pub host_network: bool,
-    #[serde(default)]
-    pub host_pid: bool,
+    pub host_aliases: Option<Vec<serde_json::Value>>,
+    #[serde(rename = "hostIPC", alias = "hostIpc")]
+    pub host_ipc: Option<bool>,
+    pub host_network: Option<bool>,
+    #[serde(rename = "hostPID", alias = "hostPid")]
+    pub host_pid: Option<bool>,
     pub host_users: Option<bool>,
-    #[serde(default)]
-    pub image_pull_secrets: Vec<String>,
-    #[serde(default)]
-    pub labels: HashMap<String, String>,
-    #[serde(default)]
-    pub node_selector: HashMap<String, String>,
+    pub image_pull_secrets: Option<Vec<String>>,
+    pub labels: Option<BTreeMap<String, String>>,
+    pub node_selector: Option<BTreeMap<String, String>>,
     pub priority_class_name: Option<String>,
     pub restart_policy: Option<String>,
     pub runtime_class_name: Option<String>,
     pub scheduler_name: Option<String>,
-    #[serde(default)]
     pub security_context: Option<serde_json::Value>,
     pub share_process_namespace: Option<bool>,
     pub termination_grace_period_seconds: Option<i64>,
-    #[serde(default)]
-    pub tolerations: Vec<serde_json::Value>,
-    #[serde(default)]
-    pub topology_spread_constraints: Vec<serde_json::Value>,
+    pub tolerations: Option<Vec<serde_json::Value>>,
+    pub topology_spread_constraints: Option<Vec<serde_json::Value>>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -114,9 +114,9 @@ pub struct Controller {
     #[serde(default = "default_controller_type")]
     pub r#type: String,
     #[serde(default)]
-    pub annotations: HashMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
     pub replicas: Option<i32>,
     pub strategy: Option<String>,
     pub rolling_update: Option<RollingUpdateConfig>,
@@ -135,6 +135,7 @@ pub struct Controller {
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Container {
     pub image: String,
     pub command: Option<Vec<String>>,
@@ -153,6 +154,7 @@ pub struct Container {
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EnvVar {
     pub value: Option<String>,
     pub value_from: Option<serde_json::Value>,
@@ -167,8 +169,8 @@ pub struct ContainerPort {
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ResourceRequirements {
-    pub requests: Option<HashMap<String, String>>,
-    pub limits: Option<HashMap<String, String>>,
+    pub requests: Option<BTreeMap<String, String>>,
+    pub limits: Option<BTreeMap<String, String>>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -214,6 +216,23 @@ pub struct CronJobConfig {
     pub parallelism: Option<i32>,
 }
 
+impl Default for CronJobConfig {
+    fn default() -> Self {
+        Self {
+            suspend: None,
+            concurrency_policy: default_concurrency_policy(),
+            time_zone: None,
+            schedule: default_cron_schedule(),
+            starting_deadline_seconds: None,
+            successful_jobs_history: default_successful_jobs_history(),
+            failed_jobs_history: default_failed_jobs_history(),
+            ttl_seconds_after_finished: None,
+            backoff_limit: default_backoff_limit(),
+            parallelism: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct JobConfig {
@@ -226,10 +245,26 @@ pub struct JobConfig {
     pub completion_mode: Option<String>,
 }
 
+impl Default for JobConfig {
+    fn default() -> Self {
+        Self {
+            suspend: None,
+            ttl_seconds_after_finished: None,
+            backoff_limit: default_backoff_limit(),
+            parallelism: None,
+            completions: None,
+            completion_mode: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StatefulSetConfig {
     pub pod_management_policy: Option<String>,
+    /// Governing Service, either a `service` identifier or a literal name.
+    /// A headless Service is generated when omitted.
+    pub service_name: Option<String>,
     #[serde(default)]
     pub volume_claim_templates: Vec<PersistenceItem>,
 }
@@ -240,9 +275,9 @@ pub struct ServiceAccount {
     #[serde(default = "default_enabled")]
     pub enabled: bool,
     #[serde(default)]
-    pub annotations: HashMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
     pub automount_service_account_token: Option<bool>,
     #[serde(default)]
     pub secrets: Vec<String>,
@@ -254,11 +289,11 @@ pub struct ConfigMap {
     #[serde(default = "default_enabled")]
     pub enabled: bool,
     #[serde(default)]
-    pub annotations: HashMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
     #[serde(default)]
-    pub data: HashMap<String, String>,
+    pub data: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -267,15 +302,18 @@ pub struct Secret {
     #[serde(default = "default_enabled")]
     pub enabled: bool,
     #[serde(default)]
-    pub annotations: HashMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
     #[serde(default = "default_secret_type")]
     pub r#type: String,
+    /// `data` values are already base64-encoded and are passed through as-is.
+    #[serde(default)]
+    pub encoded: bool,
     #[serde(default)]
-    pub data: HashMap<String, String>,
+    pub data: BTreeMap<String, String>,
     #[serde(default)]
-    pub string_data: HashMap<String, String>,
+    pub string_data: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -284,9 +322,9 @@ pub struct Ingress {
     #[serde(default = "default_enabled")]
     pub enabled: bool,
     #[serde(default)]
-    pub annotations: HashMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
     pub class_name: Option<String>,
     #[serde(default)]
     pub hosts: Vec<IngressHost>,
@@ -331,11 +369,14 @@ pub struct Route {
     #[serde(default = "default_enabled")]
     pub enabled: bool,
     #[serde(default)]
-    pub annotations: HashMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
+    /// Name of the Gateway to attach to when no `parentRefs` are given.
     pub class_name: Option<String>,
     #[serde(default)]
+    pub parent_refs: Vec<RouteParentRef>,
+    #[serde(default)]
     pub hosts: Vec<String>,
     #[serde(default)]
     pub rules: Vec<RouteRule>,
@@ -346,9 +387,30 @@ pub struct Route {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RouteRule {
+    #[serde(default)]
     pub matches: Vec<RouteMatch>,
+    #[serde(default)]
     pub backend_refs: Vec<RouteBackendRef>,
     pub filters: Option<Vec<serde_json::Value>>,
+    pub timeouts: Option<RouteTimeouts>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteParentRef {
+    pub name: String,
+    pub namespace: Option<String>,
+    pub section_name: Option<String>,
+    pub port: Option<i32>,
+    pub group: Option<String>,
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteTimeouts {
+    pub request: Option<String>,
+    pub backend_request: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -389,7 +451,10 @@ pub struct RouteQueryParamMatch {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RouteBackendRef {
-    pub name: String,
+    /// Identifier of a bunku `service` entry.
+    pub identifier: Option<String>,
+    pub name: Option<String>,
+    pub namespace: Option<String>,
     pub port: Option<i32>,
     pub weight: Option<i32>,
 }
@@ -408,9 +473,9 @@ pub struct Service {
     #[serde(default = "default_enabled")]
     pub enabled: bool,
     #[serde(default)]
-    pub annotations: HashMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
     #[serde(default = "default_service_type")]
     pub r#type: String,
     pub cluster_ip: Option<String>,
@@ -431,7 +496,7 @@ pub struct Service {
     pub ports: IndexMap<String, ServicePort>,
     pub controller: Option<String>,
     #[serde(default)]
-    pub extra_selector_labels: HashMap<String, String>,
+    pub extra_selector_labels: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -450,9 +515,11 @@ pub struct ServiceMonitor {
     #[serde(default = "default_enabled")]
     pub enabled: bool,
     #[serde(default)]
-    pub annotations: HashMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
+    /// Key of the `service` entry to scrape, defaults to the monitor's key.
+    pub service: Option<String>,
     #[serde(default = "default_service_monitor_interval")]
     pub interval: String,
     pub scrape_timeout: Option<String>,
@@ -476,9 +543,12 @@ pub struct NetworkPolicy {
     #[serde(default = "default_enabled")]
     pub enabled: bool,
     #[serde(default)]
-    pub annotations: HashMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
+    /// Controller whose pods the policy applies to, all pods when omitted.
+    pub controller: Option<String>,
+    pub pod_selector: Option<serde_json::Value>,
     #[serde(default)]
     pub policy_types: Vec<String>,
     #[serde(default)]
@@ -500,12 +570,17 @@ pub struct NetworkPolicyRule {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersistenceItem {
+    /// Claim name for StatefulSet `volumeClaimTemplates`, or the name of an
+    /// existing ConfigMap/Secret that bunku does not manage.
+    pub name: Option<String>,
+    /// Identifier of a bunku-managed `configMaps` or `secrets` entry.
+    pub identifier: Option<String>,
     #[serde(default = "default_enabled")]
     pub enabled: bool,
     #[serde(default)]
-    pub annotations: HashMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
     #[serde(default = "default_persistence_type")]
     pub r#type: String,
     pub storage_class: Option<String>,
@@ -514,8 +589,31 @@ pub struct PersistenceItem {
     pub access_modes: Vec<String>,
     pub data_source: Option<serde_json::Value>,
     pub data_source_ref: Option<serde_json::Value>,
+    pub default_mode: Option<i32>,
+    pub medium: Option<String>,
+    pub size_limit: Option<String>,
+    pub host_path: Option<String>,
+    pub host_path_type: Option<String>,
+    pub server: Option<String>,
+    pub path: Option<String>,
+    pub image: Option<String>,
+    pub pull_policy: Option<String>,
     #[serde(default)]
-    pub global_mounts: Vec<VolumeMount>,
+    pub global_mounts: Vec<GlobalMount>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GlobalMount {
+    pub mount_path: String,
+    pub read_only: Option<bool>,
+    pub sub_path: Option<String>,
+    /// Limit the mount to these controllers, all controllers when empty.
+    #[serde(default)]
+    pub controllers: Vec<String>,
+    /// Limit the mount to these containers, all containers when empty.
+    #[serde(default)]
+    pub containers: Vec<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -532,9 +630,9 @@ pub struct Role {
     #[serde(default = "default_enabled")]
     pub enabled: bool,
     #[serde(default)]
-    pub annotations: HashMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
     #[serde(default)]
     pub cluster_wide: bool,
     #[serde(default)]
@@ -560,9 +658,9 @@ pub struct RoleBinding {
     #[serde(default = "default_enabled")]
     pub enabled: bool,
     #[serde(default)]
-    pub annotations: HashMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
     #[serde(default)]
     pub cluster_wide: bool,
     pub role_ref: RoleRef,
@@ -573,8 +671,10 @@ pub struct RoleBinding {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RoleRef {
+    #[serde(default = "default_rbac_api_group")]
     pub api_group: String,
     pub kind: String,
+    /// A key of `rbac.roles` or the name of an existing role.
     pub name: String,
 }
 
@@ -582,11 +682,25 @@ pub struct RoleRef {
 #[serde(rename_all = "camelCase")]
 pub struct RoleSubject {
     pub kind: String,
-    pub name: String,
+    /// A key of `serviceAccount`, for `ServiceAccount` subjects.
+    pub identifier: Option<String>,
+    pub name: Option<String>,
     pub api_group: Option<String>,
     pub namespace: Option<String>,
 }
 
+/// The Namespace object for `global.namespace`, only emitted with `create`.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NamespaceConfig {
+    #[serde(default)]
+    pub create: bool,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RawResource {
@@ -599,6 +713,9 @@ pub struct RawResource {
 fn default_enabled() -> bool {
     true
 }
+fn default_rbac_api_group() -> String {
+    "rbac.authorization.k8s.io".to_string()
+}
 fn default_controller_type() -> String {
     "deployment".to_string()
 }
@@ -638,6 +755,9 @@ fn default_persistence_type() -> String {
 fn default_pod_options_strategy() -> String {
     "overwrite".to_string()
 }
+fn default_pod_options_list_strategy() -> String {
+    "append".to_string()
+}
 fn default_path_match_type() -> String {
     "PathPrefix".to_string()
 }
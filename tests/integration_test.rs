@@ -1,4 +1,5 @@
-use bunku::process_values_file;
+use bunku::output::{self, Format};
+use bunku::{RenderOptions, process_values_file, render_values_file};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fs;
@@ -12,17 +13,14 @@ fn get_resource_filename(resource: &Value) -> String {
     format!("{}-{}.json", kind, name)
 }
 
-/// Helper function to apply name prefix to resources (simulating CLI behavior)
-fn apply_name_prefix(mut resources: Vec<Value>, app_name: &str) -> Vec<Value> {
-    for resource in &mut resources {
-        if let Some(metadata) = resource.get_mut("metadata") {
-            if let Some(name) = metadata.get("name").and_then(|n| n.as_str()) {
-                let new_name = format!("{}-{}", app_name, name);
-                metadata["name"] = serde_json::Value::String(new_name);
-            }
-        }
-    }
-    resources
+/// Helper function to render an example with a release name (as `--name` does)
+fn render_with_name(app_toml_path: &str, app_name: &str) -> Vec<Value> {
+    let options = RenderOptions {
+        name: Some(app_name.to_string()),
+        ..Default::default()
+    };
+    render_values_file(app_toml_path, &options)
+        .unwrap_or_else(|e| panic!("Failed to process {}: {}", app_toml_path, e))
 }
 
 /// Helper function to load all expected JSON files from an example's output directory
@@ -62,12 +60,8 @@ fn test_example_output(example_name: &str, app_name: &str) {
         app_toml_path
     );
 
-    // Process the TOML file
-    let resources = process_values_file(&app_toml_path)
-        .unwrap_or_else(|e| panic!("Failed to process {}: {}", app_toml_path, e));
-
-    // Apply name prefix (simulating CLI behavior)
-    let actual_resources = apply_name_prefix(resources, app_name);
+    // Process the TOML file with the release name
+    let actual_resources = render_with_name(&app_toml_path, app_name);
 
     // Load expected output files
     let expected_resources = load_expected_resources(example_name);
@@ -349,8 +343,7 @@ fn test_name_prefix_functionality() {
 
     for (example_name, app_name) in &test_cases {
         let app_toml_path = format!("examples/{}/app.toml", example_name);
-        let resources = process_values_file(&app_toml_path).unwrap();
-        let prefixed_resources = apply_name_prefix(resources, app_name);
+        let prefixed_resources = render_with_name(&app_toml_path, app_name);
 
         for resource in &prefixed_resources {
             let resource_name = resource["metadata"]["name"].as_str().unwrap();
@@ -361,6 +354,10 @@ fn test_name_prefix_functionality() {
                 app_name,
                 example_name
             );
+
+            let labels = &resource["metadata"]["labels"];
+            assert_eq!(labels["app.kubernetes.io/name"], resource_name);
+            assert_eq!(labels["app.kubernetes.io/instance"], *app_name);
         }
     }
 }
@@ -382,3 +379,70 @@ fn test_default_resource_naming() {
         );
     }
 }
+
+#[test]
+fn test_output_formats_round_trip() {
+    for example in ["hello-world", "nginx", "podinfo", "full"] {
+        let resources = render_with_name(&format!("examples/{}/app.toml", example), example);
+
+        let yaml = output::stream(&resources, Format::Yaml).unwrap();
+        let documents: Vec<Value> = yaml
+            .split("---\n")
+            .skip(1)
+            .map(|document| serde_yaml_ng::from_str(document).unwrap())
+            .collect();
+        assert_eq!(documents, resources, "YAML stream for {}", example);
+
+        let ndjson = output::stream(&resources, Format::Ndjson).unwrap();
+        let lines: Vec<Value> = ndjson
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines, resources, "NDJSON stream for {}", example);
+
+        let list: Value =
+            serde_json::from_str(&output::stream(&resources, Format::JsonList).unwrap()).unwrap();
+        assert_eq!(list["kind"], "List");
+        assert_eq!(list["items"].as_array().unwrap(), &resources);
+    }
+}
+
+#[test]
+fn test_output_is_reproducible() {
+    for (example, app_name) in [
+        ("hello-world", "hello-world"),
+        ("nginx", "nginx"),
+        ("podinfo", "podinfo"),
+        ("full", "myapp"),
+    ] {
+        let app_toml_path = format!("examples/{}/app.toml", example);
+        let first = render_with_name(&app_toml_path, app_name);
+        let second = render_with_name(&app_toml_path, app_name);
+
+        for format in [Format::Yaml, Format::JsonList] {
+            assert_eq!(
+                output::stream(&first, format).unwrap(),
+                output::stream(&second, format).unwrap(),
+                "{} output for {} differs between runs",
+                format,
+                example
+            );
+        }
+    }
+
+    // Dependencies are applied before the workloads that use them
+    let kinds: Vec<Value> = render_with_name("examples/full/app.toml", "myapp")
+        .into_iter()
+        .map(|resource| resource["kind"].clone())
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            "ServiceAccount",
+            "ConfigMap",
+            "PersistentVolumeClaim",
+            "Service",
+            "Deployment"
+        ]
+    );
+}